@@ -0,0 +1,41 @@
+use std::{collections::BTreeMap, error, fmt};
+
+#[derive(Debug)]
+pub struct AliasError {
+    name: String,
+    needed: usize,
+    given: usize,
+}
+
+impl fmt::Display for AliasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "alias '{}' needs {} arguments but got {}", self.name, self.needed, self.given)
+    }
+}
+
+impl error::Error for AliasError {}
+
+// `restart-warning 5` with restart-warning = "say Server restarting in {0} minutes" becomes
+// "say Server restarting in 5 minutes", arguments without a placeholder are appended
+pub fn expand(aliases: &BTreeMap<String, String>, command: &str) -> Result<String, AliasError> {
+    let mut words = command.split_whitespace();
+    let (name, template) = match words.next().and_then(|name| aliases.get_key_value(name)) {
+        Some(alias) => alias,
+        None => return Ok(command.to_string()),
+    };
+    let args: Vec<&str> = words.collect();
+    // placeholders are numbered from {0} without gaps
+    let needed = (0..).take_while(|i| template.contains(&format!("{{{}}}", i))).count();
+    if args.len() < needed {
+        return Err(AliasError { name: name.clone(), needed, given: args.len() });
+    }
+    let mut expanded = template.clone();
+    for (i, arg) in args[..needed].iter().enumerate() {
+        expanded = expanded.replace(&format!("{{{}}}", i), arg);
+    }
+    for arg in &args[needed..] {
+        expanded.push(' ');
+        expanded.push_str(arg);
+    }
+    Ok(expanded)
+}
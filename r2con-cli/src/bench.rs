@@ -0,0 +1,111 @@
+use std::{error::Error, sync::{atomic::{AtomicUsize, Ordering}, Arc}, time::{Duration, Instant}};
+use futures_util::future::join_all;
+use serde::Serialize;
+
+use crate::{connection::{Connection, Target}, output::{OutputOptions, Verbosity}};
+
+pub struct BenchOptions {
+    pub count: usize,
+    // connections sending at the same time, the commands are shared out between them
+    pub concurrency: usize,
+    pub command: String,
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    command: String,
+    sent: usize,
+    failed: usize,
+    concurrency: usize,
+    min_ms: f64,
+    median_ms: f64,
+    p95_ms: f64,
+    max_ms: f64,
+    commands_per_second: f64,
+}
+
+// the connections are opened before the clock starts so logging in isn't part of the numbers
+pub async fn run(target: &Target, command_timeout: Option<Duration>, options: &BenchOptions, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut connections = Vec::new();
+    for _ in 0..options.concurrency {
+        let client = match target.connect().await {
+            Ok(client) => client,
+            Err(e) => {
+                output.print_error("connection", &format!("connection Error: {}", e));
+                return Err(e);
+            }
+        };
+        connections.push(Connection::new(target.clone(), client, command_timeout, None, None));
+    }
+    output.print_info(Verbosity::Normal, &format!("Sending '{}' {} times over {} connections to {}", options.command, options.count, options.concurrency, target.addr));
+
+    let next = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    let workers = connections.into_iter().map(|connection| worker(connection, &options.command, options.count, next.clone()));
+    let results = join_all(workers).await;
+    let elapsed = start.elapsed();
+
+    let mut round_trips = Vec::new();
+    let mut failed = 0;
+    for (connection, worker_round_trips, worker_failed) in results {
+        round_trips.extend(worker_round_trips);
+        failed += worker_failed;
+        let _ = connection.close().await;
+    }
+    if round_trips.is_empty() {
+        let message = format!("all {} commands failed", failed);
+        output.print_error("command", &message);
+        return Err(message.into());
+    }
+    round_trips.sort();
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let report = BenchReport {
+        command: options.command.clone(),
+        sent: round_trips.len() + failed,
+        failed,
+        concurrency: options.concurrency,
+        min_ms: millis(round_trips[0]),
+        median_ms: millis(percentile(&round_trips, 50.0)),
+        p95_ms: millis(percentile(&round_trips, 95.0)),
+        max_ms: millis(round_trips[round_trips.len()-1]),
+        commands_per_second: round_trips.len() as f64 / elapsed.as_secs_f64(),
+    };
+    print_report(&report, output);
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} of {} commands failed", failed, report.sent).into()),
+    }
+}
+
+// takes commands until all of them are handed out, a failed one doesn't stop the others
+async fn worker(mut connection: Connection, command: &str, count: usize, next: Arc<AtomicUsize>) -> (Connection, Vec<Duration>, usize) {
+    let mut round_trips = Vec::new();
+    let mut failed = 0;
+    while next.fetch_add(1, Ordering::SeqCst) < count {
+        let start = Instant::now();
+        match connection.send_command(command).await {
+            Ok(_) => round_trips.push(start.elapsed()),
+            Err(_) => failed += 1,
+        }
+    }
+    (connection, round_trips, failed)
+}
+
+// nearest rank on the sorted round trips
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn print_report(report: &BenchReport, output: &OutputOptions) {
+    if output.json {
+        println!("{}", serde_json::to_string(report).unwrap_or_default());
+        return;
+    }
+    if output.verbosity.is_quiet() {
+        return;
+    }
+    println!("Commands: {} sent, {} failed, {} at a time", report.sent, report.failed, report.concurrency);
+    println!("Round trip: min {:.2}ms, median {:.2}ms, p95 {:.2}ms, max {:.2}ms", report.min_ms, report.median_ms, report.p95_ms, report.max_ms);
+    println!("Throughput: {:.1} commands/s", report.commands_per_second);
+}
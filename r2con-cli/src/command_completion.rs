@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use rustyline::{completion::{Completer, Pair}, validate::{ValidationContext, ValidationResult, Validator}, Context, Helper, Highlighter, Hinter};
+
+use crate::connection::GameDialect;
+
+// command names mapped to the literal subcommands that can follow them
+#[derive(Debug, Default)]
+pub struct CommandSet {
+    commands: BTreeMap<String, Vec<String>>,
+}
+
+impl CommandSet {
+    pub fn parse(dialect: GameDialect, output: &str) -> CommandSet {
+        match dialect {
+            GameDialect::Minecraft => CommandSet::parse_minecraft_help(output),
+            GameDialect::Source => CommandSet::parse_cvarlist(output),
+            _ => CommandSet::default(),
+        }
+    }
+
+    // minecraft sends every usage line of `help` back to back over rcon,
+    // e.g. "/advancement (grant|revoke)/attribute <target> ..."
+    fn parse_minecraft_help(output: &str) -> CommandSet {
+        let mut set = CommandSet::default();
+        for usage in output.split('/') {
+            let mut words = usage.split_whitespace();
+            let name = match words.next() {
+                Some(name) if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':') => name,
+                _ => continue,
+            };
+            let subcommands = set.commands.entry(name.to_string()).or_default();
+            match words.next() {
+                Some(options) if options.starts_with('(') => {
+                    let options = options.trim_start_matches('(').trim_end_matches(')');
+                    subcommands.extend(options.split('|').map(|option| option.to_string()));
+                }
+                Some(literal) if literal.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                    subcommands.push(literal.to_string());
+                }
+                _ => {}
+            }
+        }
+        for subcommands in set.commands.values_mut() {
+            subcommands.sort();
+            subcommands.dedup();
+        }
+        set
+    }
+
+    // "sv_cheats : 0 : , "nolog", "rep" : Allow cheats on server", framed by a header and a total count
+    fn parse_cvarlist(output: &str) -> CommandSet {
+        let mut set = CommandSet::default();
+        for line in output.lines() {
+            if !line.contains(" : ") {
+                continue;
+            }
+            if let Some(name) = line.split_whitespace().next() {
+                set.commands.entry(name.to_string()).or_default();
+            }
+        }
+        set
+    }
+
+    fn complete(&self, line: &str) -> (usize, Vec<Pair>) {
+        let pair = |candidate: &String| Pair { display: candidate.clone(), replacement: candidate.clone() };
+        match line.split_once(' ') {
+            None => {
+                let matches = self.commands.keys().filter(|name| name.starts_with(line)).map(pair).collect();
+                (0, matches)
+            }
+            Some((command, rest)) if !rest.contains(' ') => {
+                let matches = match self.commands.get(command) {
+                    Some(subcommands) => subcommands.iter().filter(|sub| sub.starts_with(rest)).map(pair).collect(),
+                    None => Vec::new(),
+                };
+                (command.len() + 1, matches)
+            }
+            Some(_) => (line.len(), Vec::new()),
+        }
+    }
+}
+
+#[derive(Helper, Hinter, Highlighter, Default)]
+pub struct ReplHelper {
+    pub commands: CommandSet,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.commands.complete(&line[..pos]))
+    }
+}
+
+// Enter inserts a newline instead of sending while the input ends with a backslash
+// or has brackets left open, e.g. half of a tellraw JSON component
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match needs_continuation(ctx.input()) {
+            true => Ok(ValidationResult::Incomplete),
+            false => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+fn needs_continuation(input: &str) -> bool {
+    if input.ends_with('\\') {
+        return true;
+    }
+    // quotes only count inside brackets, outside of them they are apostrophes in chat messages
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in input.chars() {
+        if let Some(open) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == open => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth -= 1,
+            '"' | '\'' if depth > 0 => quote = Some(c),
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+// the lines of a multi-line input as the one command that is sent, a backslash before a line break
+// joins the lines directly and other line breaks become a space
+pub fn join_lines(input: &str) -> String {
+    let mut command = String::new();
+    for (i, line) in input.split('\n').enumerate() {
+        let line = match i {
+            0 => line,
+            _ => line.trim_start(),
+        };
+        if !command.is_empty() && !command.ends_with('\\') && !line.is_empty() {
+            command.push(' ');
+        }
+        if command.ends_with('\\') {
+            command.pop();
+        }
+        command.push_str(line);
+    }
+    command
+}
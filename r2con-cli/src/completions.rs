@@ -0,0 +1,55 @@
+use std::{io, process::ExitCode};
+use clap::ValueEnum;
+use clap_complete::{engine::CompletionCandidate, env::Shells};
+
+use crate::{profile::ConfigFile, EXIT_USAGE};
+
+// the generated scripts call back into r2con through this variable, see CompleteEnv
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Elvish,
+}
+
+impl Shell {
+    fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Powershell => "powershell",
+            Shell::Elvish => "elvish",
+        }
+    }
+}
+
+pub fn run_completions(shell: Shell) -> ExitCode {
+    let shells = Shells::builtins();
+    let completer = match shells.completer(shell.name()) {
+        Some(completer) => completer,
+        None => {
+            eprintln!("error: completions are not supported for {}", shell.name());
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match completer.write_registration(COMPLETE_VAR, "r2con", "r2con", "r2con", &mut io::stdout()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+// read when the shell asks for completions, so newly added profiles show up right away
+pub fn profile_names() -> Vec<CompletionCandidate> {
+    match ConfigFile::load() {
+        Ok(config) => config.profiles.keys().map(CompletionCandidate::new).collect(),
+        Err(_) => Vec::new(),
+    }
+}
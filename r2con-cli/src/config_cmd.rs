@@ -0,0 +1,153 @@
+use std::process::ExitCode;
+use clap::Subcommand;
+use clap_complete::engine::ArgValueCandidates;
+
+use crate::{connection::GameDialect, profile::{ConfigFile, Profile}, EXIT_USAGE};
+#[cfg(feature = "keyring")]
+use crate::profile::{delete_keyring_password, store_keyring_password};
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Add a profile or replace an existing one, the password is prompted for
+    Add {
+        /// Profile name
+        name: String,
+        /// RCON server hostname
+        #[arg(short = 'H', long)]
+        host: String,
+        /// RCON port
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        port: Option<u16>,
+        /// Program printing the password, used instead of storing it
+        #[arg(long)]
+        password_command: Option<String>,
+        /// Game specific protocol quirks
+        #[arg(long, value_enum)]
+        dialect: Option<GameDialect>,
+        /// Wait time between commands in seconds
+        #[arg(short, long, value_parser = crate::parse_seconds)]
+        wait_time: Option<f64>,
+        /// Store the password in the OS credential store instead of the config file (needs the keyring feature)
+        #[arg(long, conflicts_with = "password_command")]
+        use_keyring: bool,
+    },
+    /// List the saved profiles
+    List,
+    /// Remove a profile
+    Remove {
+        /// Profile name
+        #[arg(add = ArgValueCandidates::new(crate::completions::profile_names))]
+        name: String,
+    },
+    /// Use a profile when neither --profile nor --host is given
+    SetDefault {
+        /// Profile name
+        #[arg(add = ArgValueCandidates::new(crate::completions::profile_names))]
+        name: String,
+    },
+}
+
+pub fn run_config(action: ConfigAction, quiet: bool) -> ExitCode {
+    let mut config = match ConfigFile::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    match action {
+        ConfigAction::Add { name, host, port, password_command, dialect, wait_time, use_keyring } => {
+            if use_keyring && !cfg!(feature = "keyring") {
+                eprintln!("error: r2con was built without the keyring feature");
+                return ExitCode::from(EXIT_USAGE);
+            }
+            let password = match password_command {
+                Some(_) => None,
+                None => match rpassword::prompt_password("Password (leave empty to be asked when connecting): ") {
+                    Ok(password) if password.is_empty() => None,
+                    Ok(password) => Some(password),
+                    Err(e) => {
+                        eprintln!("error: could not read the password: {}", e);
+                        return ExitCode::from(EXIT_USAGE);
+                    }
+                },
+            };
+            #[cfg(feature = "keyring")]
+            let password = match (use_keyring, password) {
+                (true, Some(password)) => match store_keyring_password(&name, &password) {
+                    Ok(_) => None,
+                    Err(e) => {
+                        eprintln!("error: could not store the password in the keyring: {}", e);
+                        return ExitCode::from(EXIT_USAGE);
+                    }
+                },
+                (_, password) => password,
+            };
+            let profile = Profile {
+                host: Some(host),
+                port,
+                password,
+                password_command,
+                use_keyring,
+                dialect,
+                wait_time,
+                prompt: None,
+            };
+            config.profiles.insert(name, profile);
+        }
+        ConfigAction::List => {
+            if !quiet {
+                for (name, profile) in &config.profiles {
+                    let marker = if config.default.as_ref() == Some(name) { " (default)" } else { "" };
+                    let host = profile.host.clone().unwrap_or_default();
+                    match profile.port {
+                        Some(port) => println!("{}{}: {}:{}", name, marker, host, port),
+                        None => println!("{}{}: {}", name, marker, host),
+                    }
+                }
+            }
+            return ExitCode::SUCCESS;
+        }
+        ConfigAction::Remove { name } => {
+            let profile = match config.profiles.remove(&name) {
+                Some(profile) => profile,
+                None => {
+                    eprintln!("error: no profile named '{}'", name);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+            #[cfg(feature = "keyring")]
+            if profile.use_keyring {
+                if let Err(e) = delete_keyring_password(&name) {
+                    eprintln!("warning: could not remove the password from the keyring: {}", e);
+                }
+            }
+            #[cfg(not(feature = "keyring"))]
+            let _ = profile;
+            if config.default.as_ref() == Some(&name) {
+                config.default = None;
+            }
+        }
+        ConfigAction::SetDefault { name } => {
+            if !config.profiles.contains_key(&name) {
+                eprintln!("error: no profile named '{}'", name);
+                return ExitCode::from(EXIT_USAGE);
+            }
+            config.default = Some(name);
+        }
+    }
+
+    match config.save() {
+        Ok(path) => {
+            if !quiet {
+                println!("Saved {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: could not save the config file: {}", e);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
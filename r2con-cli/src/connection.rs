@@ -0,0 +1,335 @@
+use std::{error::Error, fmt, future::Future, io, mem, time::{Duration, Instant}};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::time::timeout;
+
+use r2con::{ark, minecraft, source, BattlEyeClient, ConnectionClosedError, Dialect, Encoding, RconAuthError, RconClient, RconConfig, RconError, WebRconClient};
+
+use crate::{recording::Recorder, session_log::SessionLog};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    /// Source RCON (Minecraft, Source engine)
+    Rcon,
+    /// WebSocket RCON (Rust)
+    Webrcon,
+    /// BattlEye RCON (DayZ, Arma)
+    Battleye,
+}
+
+impl Protocol {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Protocol::Rcon => 25575,
+            Protocol::Webrcon => 28016,
+            Protocol::Battleye => 2306,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TextEncoding {
+    /// Strict UTF-8, fails on invalid bytes
+    Utf8,
+    /// UTF-8 with invalid bytes replaced
+    Lossy,
+    /// ISO-8859-1
+    Latin1,
+    /// Windows-1252 (Source engine)
+    Cp1252,
+}
+
+impl From<TextEncoding> for Encoding {
+    fn from(value: TextEncoding) -> Encoding {
+        match value {
+            TextEncoding::Utf8 => Encoding::Utf8,
+            TextEncoding::Lossy => Encoding::Lossy,
+            TextEncoding::Latin1 => Encoding::Latin1,
+            TextEncoding::Cp1252 => Encoding::Cp1252,
+        }
+    }
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GameDialect {
+    /// Minecraft
+    Minecraft,
+    /// Source engine servers (CS, TF2, Garry's Mod)
+    Source,
+    /// Factorio, no dummy terminator packets
+    Factorio,
+    /// ARK: Survival Evolved
+    Ark,
+    /// Palworld, single packet responses with a timeout
+    Palworld,
+}
+
+impl GameDialect {
+    // lists the server's commands for tab completion in interactive mode
+    pub fn help_command(&self) -> Option<&'static str> {
+        match self {
+            GameDialect::Minecraft => Some("help"),
+            GameDialect::Source => Some("cvarlist"),
+            _ => None,
+        }
+    }
+
+    // lists the players, cheap enough to double as a liveness probe
+    pub fn players_command(&self) -> &'static str {
+        match self {
+            GameDialect::Minecraft => "list",
+            GameDialect::Source => "status",
+            GameDialect::Ark => "ListPlayers",
+            GameDialect::Palworld => "ShowPlayers",
+            GameDialect::Factorio => "/players online count",
+        }
+    }
+
+    // online players and the maximum when the game reports it
+    pub fn player_count(&self, output: &str) -> Option<(u32, Option<u32>)> {
+        match self {
+            GameDialect::Minecraft => minecraft::parse_list(output).map(|list| (list.online, Some(list.max))),
+            GameDialect::Source => source::parse_status(output).map(|status| (status.humans, Some(status.max_players))),
+            GameDialect::Ark => ark::parse_list_players(output).map(|players| (players.len() as u32, None)),
+            // a header line followed by one line per player
+            GameDialect::Palworld => Some((output.lines().filter(|line| !line.trim().is_empty()).count().saturating_sub(1) as u32, None)),
+            GameDialect::Factorio => output.trim().strip_prefix("Online players (")
+                .and_then(|rest| rest.split_once(')'))
+                .and_then(|(count, _)| count.parse().ok())
+                .map(|count| (count, None)),
+        }
+    }
+}
+
+impl From<GameDialect> for Dialect {
+    fn from(value: GameDialect) -> Dialect {
+        match value {
+            GameDialect::Minecraft => Dialect::Minecraft,
+            GameDialect::Source => Dialect::Source,
+            GameDialect::Factorio => Dialect::Factorio,
+            GameDialect::Ark => Dialect::Ark,
+            GameDialect::Palworld => Dialect::Palworld,
+        }
+    }
+}
+
+pub enum Client {
+    Rcon(RconClient),
+    WebRcon(WebRconClient),
+    BattlEye(BattlEyeClient),
+}
+
+impl Client {
+    async fn send_command(&mut self, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        match self {
+            Client::Rcon(client) => client.send_command(command).await,
+            Client::WebRcon(client) => client.send_command(command).await,
+            Client::BattlEye(client) => client.send_command(command).await,
+        }
+    }
+
+    // the other protocols carry text, only rcon bodies can hold arbitrary bytes
+    async fn send_command_raw(&mut self, command: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Client::Rcon(client) => client.send_command_raw(command).await,
+            client => client.send_command(command).await.map(String::into_bytes),
+        }
+    }
+
+    // nothing is sent when the connection was used recently or the server doesn't drop idle ones
+    async fn keepalive(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            Client::Rcon(client) if client.needs_keepalive() => client.keepalive().await,
+            Client::BattlEye(client) if client.needs_keepalive() => client.keepalive().await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn close(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            Client::Rcon(client) => client.close().await,
+            Client::WebRcon(client) => client.close().await,
+            Client::BattlEye(_) => Ok(()),
+        }
+    }
+}
+
+// where and how to connect, kept so the connection can be opened again
+#[derive(Clone)]
+pub struct Target {
+    pub protocol: Protocol,
+    // only used by the rcon protocol
+    pub rcon_config: RconConfig,
+    pub addr: String,
+    pub password: String,
+    pub connect_timeout: Option<Duration>,
+}
+
+impl Target {
+    pub async fn connect(&self) -> Result<Client, Box<dyn Error + Send + Sync>> {
+        let connect = async {
+            match self.protocol {
+                Protocol::Rcon => self.rcon_config.connect(&self.addr, &self.password).await.map(Client::Rcon),
+                Protocol::Webrcon => WebRconClient::connect(&self.addr, &self.password).await.map(Client::WebRcon),
+                Protocol::Battleye => BattlEyeClient::connect(&self.addr, &self.password).await.map(Client::BattlEye),
+            }
+        };
+        let result = match self.connect_timeout {
+            Some(deadline) => match timeout(deadline, connect).await {
+                Ok(result) => result,
+                Err(_) => Err(RconError::Timeout(deadline).into()),
+            },
+            None => connect.await,
+        };
+        // auth failures and timeouts keep their type, callers check for them
+        result.map_err(|e| match e.is::<RconAuthError>() || is_timeout(e.as_ref()) {
+            true => e,
+            false => ConnectError { source: e }.into(),
+        })
+    }
+}
+
+// the server couldn't be reached, whatever the protocol reported it with
+#[derive(Debug)]
+pub struct ConnectError {
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Error for ConnectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+// a client of any protocol together with the settings the CLI applies on top of it
+pub struct Connection {
+    target: Target,
+    client: Client,
+    command_timeout: Option<Duration>,
+    log: Option<SessionLog>,
+    recorder: Option<Recorder>,
+}
+
+impl Connection {
+    pub fn new(target: Target, client: Client, command_timeout: Option<Duration>, log: Option<SessionLog>, recorder: Option<Recorder>) -> Connection {
+        Connection { target, client, command_timeout, log, recorder }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    pub fn command_timeout(&self) -> Option<Duration> {
+        self.command_timeout
+    }
+
+    pub fn set_command_timeout(&mut self, command_timeout: Option<Duration>) {
+        self.command_timeout = command_timeout;
+    }
+
+    // logs in again with a new client, the old one is closed afterwards
+    pub async fn reconnect(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let client = self.target.connect().await?;
+        // the old connection is most likely dead already
+        let _ = mem::replace(&mut self.client, client).close().await;
+        Ok(())
+    }
+
+    pub async fn send_command(&mut self, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        if let Some(log) = &self.log {
+            log.command(command);
+        }
+        let sent = Instant::now();
+        let result = with_timeout(self.command_timeout, self.client.send_command(command)).await;
+        self.note_result(command, sent, &result);
+        result
+    }
+
+    // for connections that sit idle between commands, e.g. in serve, so servers like ARK don't drop them
+    pub async fn keepalive(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        with_timeout(self.command_timeout, self.client.keepalive()).await
+    }
+
+    // for commands r2con sends on its own, e.g. the completion probe, which the session log
+    // and the recording would otherwise show as typed by the user and replay would send again
+    pub async fn send_unrecorded(&mut self, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        with_timeout(self.command_timeout, self.client.send_command(command)).await
+    }
+
+    // the response bytes as the server sent them, without decoding or color handling
+    pub async fn send_command_raw(&mut self, command: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        if let Some(log) = &self.log {
+            log.command(command);
+        }
+        let sent = Instant::now();
+        let result = with_timeout(self.command_timeout, self.client.send_command_raw(command)).await;
+        let text = match &result {
+            Ok(bytes) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            Err(e) => Err(e.to_string().into()),
+        };
+        self.note_result(command, sent, &text);
+        result
+    }
+
+    fn note_result(&self, command: &str, sent: Instant, result: &Result<String, Box<dyn Error + Send + Sync>>) {
+        if let Some(log) = &self.log {
+            match result {
+                Ok(output) => log.response(output),
+                Err(e) => log.error(&e.to_string()),
+            }
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(command, sent, result);
+        }
+    }
+
+    pub async fn close(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(log) = &self.log {
+            log.flush();
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.flush();
+        }
+        self.client.close().await
+    }
+
+    pub fn get_address(&self) -> Option<String> {
+        match &self.client {
+            Client::Rcon(client) => client.get_address().ok().map(|addr| addr.to_string()),
+            Client::WebRcon(client) => Some(client.get_address().to_string()),
+            Client::BattlEye(client) => client.get_address().ok().map(|addr| addr.to_string()),
+        }
+    }
+}
+
+async fn with_timeout<T>(deadline: Option<Duration>, command: impl Future<Output = Result<T, Box<dyn Error + Send + Sync>>>) -> Result<T, Box<dyn Error + Send + Sync>> {
+    match deadline {
+        Some(deadline) => match timeout(deadline, command).await {
+            Ok(result) => result,
+            Err(_) => Err(RconError::Timeout(deadline).into()),
+        },
+        None => command.await,
+    }
+}
+
+pub fn is_timeout(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    matches!(e.downcast_ref::<RconError>(), Some(RconError::Timeout(_)))
+}
+
+// the server went away, e.g. it restarted, rather than rejecting the command
+pub fn is_disconnect(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    if e.is::<ConnectionClosedError>() {
+        return true;
+    }
+    match e.downcast_ref::<io::Error>() {
+        Some(e) => matches!(e.kind(), io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof),
+        None => false,
+    }
+}
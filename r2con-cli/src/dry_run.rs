@@ -0,0 +1,63 @@
+use std::process::ExitCode;
+
+use r2con::rcon_packet::{Packet, PacketType};
+
+use crate::{address::HostTarget, connection::{GameDialect, Protocol}, EXIT_USAGE};
+
+// what a run would do, printed instead of connecting
+pub struct Plan<'a> {
+    pub profile: Option<&'a str>,
+    pub targets: &'a [HostTarget],
+    pub protocol: Protocol,
+    pub dialect: GameDialect,
+    pub password_given: bool,
+    pub commands: &'a [String],
+    pub hexdump: bool,
+}
+
+pub fn run(plan: &Plan) -> ExitCode {
+    if let Some(profile) = plan.profile {
+        println!("Profile: {}", profile);
+    }
+    for target in plan.targets {
+        if target.label == target.addr {
+            println!("Host: {}", target.addr);
+        } else {
+            println!("Host: {} ({})", target.label, target.addr);
+        }
+    }
+    match plan.protocol {
+        Protocol::Rcon => println!("Protocol: rcon ({:?} dialect)", plan.dialect),
+        protocol => println!("Protocol: {}", format!("{:?}", protocol).to_lowercase()),
+    }
+    // the password itself is never printed
+    println!("Password: {}", if plan.password_given { "given" } else { "would be prompted for" });
+    if plan.commands.is_empty() {
+        println!("No commands, interactive mode would start");
+    }
+    for (i, command) in plan.commands.iter().enumerate() {
+        println!("Command {}: {}", i+1, command);
+        if plan.hexdump && plan.protocol == Protocol::Rcon {
+            // ids are only placeholders, real ones are picked when sending
+            match Packet::with_id(i as i32 + 1, PacketType::Command, command) {
+                Ok(packet) => print!("{}", hexdump(&Vec::from(&packet))),
+                Err(e) => {
+                    eprintln!("error: '{}' can't be sent: {}", command, e);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// offset, 16 bytes in hex, then the printable ones
+fn hexdump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }).collect();
+        dump.push_str(&format!("  {:08x}  {:<47}  |{}|\n", row*16, hex.join(" "), text));
+    }
+    dump
+}
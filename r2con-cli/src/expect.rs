@@ -0,0 +1,34 @@
+use std::{error, fmt};
+use regex::Regex;
+
+// output checks for health probes: every response must match `all`, the i-th command's response the i-th of `each`
+#[derive(Debug, Default)]
+pub struct Expectations {
+    pub all: Option<Regex>,
+    pub each: Vec<Regex>,
+}
+
+#[derive(Debug)]
+pub struct ExpectError {
+    command: String,
+    pattern: String,
+}
+
+impl fmt::Display for ExpectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the response to '{}' did not match /{}/", self.command, self.pattern)
+    }
+}
+
+impl error::Error for ExpectError {}
+
+impl Expectations {
+    pub fn check(&self, index: usize, command: &str, output: &str) -> Result<(), ExpectError> {
+        for pattern in self.all.iter().chain(self.each.get(index)) {
+            if !pattern.is_match(output) {
+                return Err(ExpectError { command: command.to_string(), pattern: pattern.to_string() });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, error::Error, fmt::Write, net::SocketAddr, process::ExitCode, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use r2con::RconAuthError;
+
+use crate::{connection::{Connection, GameDialect, Target}, profile::ConfigFile, EXIT_CONNECTION, EXIT_USAGE};
+
+type Metrics = Arc<Mutex<BTreeMap<String, ServerMetrics>>>;
+
+// the result of the last probe of a profile
+#[derive(Clone, Default)]
+struct ServerMetrics {
+    addr: String,
+    up: bool,
+    auth_ok: bool,
+    rtt: Option<Duration>,
+    players_online: Option<u32>,
+    players_max: Option<u32>,
+}
+
+// probes every profile in the background and serves the latest results on /metrics
+pub async fn run_export(listen: SocketAddr, interval: Duration, profiles: Vec<String>, connect_timeout: Option<Duration>, command_timeout: Option<Duration>) -> ExitCode {
+    let config = match ConfigFile::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let names = match config.profile_names(profiles) {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let metrics: Metrics = Arc::new(Mutex::new(BTreeMap::new()));
+    for name in names {
+        let (target, dialect) = match config.target(&name, connect_timeout).await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("error: profile '{}': {}", name, e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        };
+        tokio::spawn(probe_loop(name, target, dialect, interval, command_timeout, metrics.clone()));
+    }
+
+    let app = Router::new().route("/metrics", get(serve_metrics)).with_state(metrics);
+    let listener = match tokio::net::TcpListener::bind(listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: could not listen on {}: {}", listen, e);
+            return ExitCode::from(EXIT_CONNECTION);
+        }
+    };
+    eprintln!("serving metrics on http://{}/metrics", listen);
+    match axum::serve(listener, app).await {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_CONNECTION)
+        }
+    }
+}
+
+// the probe that failed and one more on a new connection
+const PROBE_ATTEMPTS: usize = 2;
+
+// keeps one connection per profile open between probes and reconnects when it breaks
+async fn probe_loop(name: String, target: Target, dialect: GameDialect, interval: Duration, command_timeout: Option<Duration>, metrics: Metrics) {
+    let mut connection: Option<Connection> = None;
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let mut result = ServerMetrics { addr: target.addr.clone(), ..Default::default() };
+        // a connection the server dropped since the last tick fails the first probe,
+        // the fresh one decides whether the server is down
+        for _ in 0..PROBE_ATTEMPTS {
+            if connection.is_none() {
+                match target.connect().await {
+                    Ok(client) => connection = Some(Connection::new(target.clone(), client, command_timeout, None, None)),
+                    // the server answered, it just didn't accept the password
+                    Err(e) if e.is::<RconAuthError>() => {
+                        result.up = true;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+            let open = connection.as_mut().unwrap();
+            match probe(open, dialect).await {
+                Ok((rtt, players)) => {
+                    result.up = true;
+                    result.auth_ok = true;
+                    result.rtt = Some(rtt);
+                    if let Some((online, max)) = players {
+                        result.players_online = Some(online);
+                        result.players_max = max;
+                    }
+                    break;
+                }
+                Err(_) => {
+                    if let Some(broken) = connection.take() {
+                        let _ = broken.close().await;
+                    }
+                }
+            }
+        }
+        metrics.lock().unwrap().insert(name.clone(), result);
+    }
+}
+
+async fn probe(connection: &mut Connection, dialect: GameDialect) -> Result<(Duration, Option<(u32, Option<u32>)>), Box<dyn Error + Send + Sync>> {
+    let start = Instant::now();
+    let output = connection.send_command(dialect.players_command()).await?;
+    Ok((start.elapsed(), dialect.player_count(&output)))
+}
+
+async fn serve_metrics(State(metrics): State<Metrics>) -> impl IntoResponse {
+    let body = render(&metrics.lock().unwrap());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+// prometheus text exposition format, servers without a value for a gauge are left out of it
+fn render(metrics: &BTreeMap<String, ServerMetrics>) -> String {
+    let gauges: [(&str, &str, fn(&ServerMetrics) -> Option<f64>); 5] = [
+        ("r2con_up", "Whether the server accepted a connection", |server| Some(server.up as u8 as f64)),
+        ("r2con_auth_ok", "Whether the server accepted the password", |server| Some(server.auth_ok as u8 as f64)),
+        ("r2con_rtt_seconds", "Round trip time of the player list command", |server| server.rtt.map(|rtt| rtt.as_secs_f64())),
+        ("r2con_players_online", "Players online", |server| server.players_online.map(f64::from)),
+        ("r2con_players_max", "Player slots", |server| server.players_max.map(f64::from)),
+    ];
+    let mut body = String::new();
+    for (name, help, value) in gauges {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+        for (profile, server) in metrics {
+            if let Some(value) = value(server) {
+                let _ = writeln!(body, "{}{{profile=\"{}\",addr=\"{}\"}} {}", name, escape_label(profile), escape_label(&server.addr), value);
+            }
+        }
+    }
+    body
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,37 +1,335 @@
-use clap::{arg, command, value_parser, Args, Command, Parser};
-use std::{env, error::Error, io::Write, process::ExitCode, time::Duration};
-use tokio::{io::{self, AsyncBufReadExt, AsyncRead, BufReader, Lines}, time::sleep};
+use clap::{command, parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::{collections::BTreeMap, env, error::Error, io::{IsTerminal, Write}, net::SocketAddr, path::{Path, PathBuf}, process::ExitCode, time::{Duration, Instant}};
+use futures_util::{stream, StreamExt};
+use rand::Rng;
+use regex::Regex;
+use tracing_subscriber::filter::LevelFilter;
+use tokio::{io::{self, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader, Lines}, time::sleep};
 
-use r2con::{RconAuthError, RconClient};
+use r2con::{dns::resolve_minecraft_srv, ColorMode, RconAuthError, RconConfig, Socks5Config};
 
-const DEFAULT_PORT: i32 = 25575;
+mod address;
+mod alias;
+mod bench;
+mod command_completion;
+mod completions;
+mod config_cmd;
+mod connection;
+mod dry_run;
+mod expect;
+mod export;
+mod output;
+mod output_files;
+mod ping;
+mod profile;
+mod recording;
+mod query;
+mod repl;
+mod serve;
+mod session_log;
+mod shutdown;
+mod table;
+mod tui;
+mod vars;
+mod watch;
+
+use address::{HostSpec, HostTarget};
+use bench::BenchOptions;
+use clap_complete::{engine::ArgValueCandidates, CompleteEnv};
+use completions::{Shell, COMPLETE_VAR};
+use config_cmd::ConfigAction;
+use connection::{is_disconnect, is_timeout, ConnectError, Connection, GameDialect, Protocol, Target, TextEncoding};
+use expect::Expectations;
+use output::{ColorChoice, OutputOptions, Verbosity};
+use output_files::OutputFiles;
+use profile::{run_password_command, ConfigFile, HistorySettings, Profile};
+use recording::Recorder;
+use repl::{ReplOptions, DEFAULT_PROMPT};
+use session_log::SessionLog;
+use shutdown::{Shutdown, Terminated};
+use table::OutputFormat;
+use watch::WatchOptions;
+
+// documented in the README so scripts can tell a wrong password from a server that is down,
+// anything not covered exits with 1 and signals with 128 + the signal number,
+// input that can't be read or files that can't be written count as usage errors
+const EXIT_AUTH: u8 = 2;
+// refused, unreachable, dropped or too slow to answer
+const EXIT_CONNECTION: u8 = 3;
+const EXIT_COMMAND: u8 = 4;
+const EXIT_USAGE: u8 = 5;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about=None)]
+#[command(name = "r2con", version, about, long_about=None)]
 struct Cli {
-    /// RCON server hostname
+    /// RCON server hostname, host:port overrides the port and rcon://password@host:port also gives the password,
+    /// repeat it to run the commands on several servers
     #[arg(short = 'H', long)]
-    host: Option<String>,
+    host: Vec<String>,
+
+    /// RCON port [default: 25575, 28016 for webrcon, 2306 for battleye]
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+    port: Option<u16>,
+
+    /// Comma separated list of servers to run the commands on (server1,server2:25580)
+    #[arg(long, value_delimiter = ',')]
+    hosts: Vec<String>,
 
     /// RCON server password
     #[arg(short = 'P', long)]
     password: Option<String>,
 
-    /// Supress output
-    #[arg(short, long, default_value_t = false)]
-    silent: bool,
+    /// Read the password from the first line of a file
+    #[arg(long, conflicts_with = "password")]
+    password_file: Option<PathBuf>,
+
+    /// Run a program and use the first line it prints as the password (e.g. "pass show mc/rcon")
+    #[arg(long, conflicts_with_all = ["password", "password_file"])]
+    password_command: Option<String>,
+
+    /// Only print errors, not responses (--silent and -s are kept as aliases)
+    #[arg(short, long, alias = "silent", short_alias = 's', default_value_t = false)]
+    quiet: bool,
+
+    /// Print connection details, -vv also prints every packet
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
 
     /// Wait time between commands in seconds
     /// (only affects non-interactive mode)
-    #[arg(short, long, default_value_t = 0.0)]
+    #[arg(short, long, default_value_t = 0.0, value_parser = parse_seconds)]
     wait_time: f64,
 
+    /// Run the remaining commands after one fails and report the failed ones at the end
+    #[arg(long, default_value_t = false, overrides_with = "fail_fast")]
+    keep_going: bool,
+
+    /// Stop at the first failing command (the default)
+    #[arg(long, default_value_t = false, overrides_with = "keep_going")]
+    fail_fast: bool,
+
+    /// Fail unless every response matches this regex, e.g. "There are \d+" for a health check
+    #[arg(long)]
+    expect: Option<Regex>,
+
+    /// Regex the response of the command at the same position has to match (can be repeated)
+    #[arg(long)]
+    expect_each: Vec<Regex>,
+
+    /// Print the resolved hosts, profile and commands without connecting
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// With --dry-run, also print the packets that would be sent as hexdumps (rcon protocol only)
+    #[arg(long, requires = "dry_run", default_value_t = false)]
+    hexdump: bool,
+
+    /// Add a random delay of up to this many seconds to every wait
+    #[arg(long, default_value_t = 0.0, value_parser = parse_seconds)]
+    wait_jitter: f64,
+
     /// Enable interactive mode after commands are finished
     #[arg(short, long, default_value_t = false)]
     interactive: bool,
 
+    /// SOCKS5 proxy to connect through ([user:pass@]host:port)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Protocol used to talk to the server
+    #[arg(long, value_enum, default_value_t = Protocol::Rcon)]
+    protocol: Protocol,
+
+    /// Text encoding used to decode responses, overrides the dialect's (rcon protocol only)
+    #[arg(long, value_enum, default_value_t = TextEncoding::Lossy)]
+    encoding: TextEncoding,
+
+    /// Game specific protocol quirks (rcon protocol only)
+    #[arg(long, value_enum, default_value_t = GameDialect::Minecraft)]
+    dialect: GameDialect,
+
+    /// Look up the host's _minecraft._tcp SRV record when no port is given
+    #[arg(long, default_value_t = false)]
+    srv: bool,
+
+    /// Convert Minecraft § and Source color codes in responses to ANSI colors or strip them
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Seconds to wait for the connection and login, 0 waits forever
+    #[arg(long, default_value_t = 10.0, value_parser = parse_seconds)]
+    connect_timeout: f64,
+
+    /// Seconds to wait for each command's response, 0 waits forever
+    #[arg(long, default_value_t = 30.0, value_parser = parse_seconds)]
+    command_timeout: f64,
+
+    /// Retry the initial connection this many times before giving up, e.g. while a server restarts
+    #[arg(long, default_value_t = 0)]
+    retries: u32,
+
+    /// Seconds to wait between connection retries
+    #[arg(long, default_value_t = 2.0, value_parser = parse_seconds)]
+    retry_delay: f64,
+
+    /// Run the commands again every given number of seconds over the same connection until Ctrl-C
+    #[arg(long, value_parser = parse_seconds)]
+    watch: Option<f64>,
+
+    /// Clear the screen before each --watch run
+    #[arg(long, requires = "watch", default_value_t = false)]
+    clear: bool,
+
+    /// Highlight lines that changed since the previous --watch run
+    #[arg(long, requires = "watch", default_value_t = false)]
+    diff: bool,
+
+    /// Run on up to N hosts at the same time instead of one after the other (8 when no N is given)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "8")]
+    parallel: Option<usize>,
+
+    /// Append a timestamped transcript of every command and response to a file, even with --quiet
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Print one JSON object per command on stdout and errors as JSON on stderr
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// Write responses byte for byte as received: no trimming, color handling or added newlines
+    #[arg(long, default_value_t = false, conflicts_with_all = ["json", "watch"])]
+    raw: bool,
+
+    /// Show player and ban lists (list, whitelist list, banlist, Source status, ARK ListPlayers) as a table or CSV
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with_all = ["json", "raw"])]
+    format: OutputFormat,
+
+    /// Only print the lines of each response matching this regex, also applies to --json output
+    #[arg(long, conflicts_with_all = ["raw", "format"])]
+    grep: Option<Regex>,
+
+    /// Print the lines not matching --grep instead
+    #[arg(long, default_value_t = false, requires = "grep")]
+    invert: bool,
+
+    /// Also write each command's response to a file of its own in this directory, add -q to skip stdout
+    #[arg(long, conflicts_with = "watch")]
+    output_dir: Option<PathBuf>,
+
+    /// Names of the --output-dir files, {index}, {command} and {host} are replaced
+    #[arg(long, default_value = output_files::DEFAULT_TEMPLATE, requires = "output_dir")]
+    output_template: String,
+
+    /// Value for {{key}} placeholders in commands, ${NAME} is read from the environment (can be repeated)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = vars::parse_var)]
+    var: Vec<(String, String)>,
+
+    /// Save every command with its timing and response as JSON lines, for `r2con replay`
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Interactive prompt, {host}, {port}, {profile} and {latency} are replaced [default: "{host}:{port}> "]
+    #[arg(long)]
+    prompt: Option<String>,
+
+    /// Don't log in again on its own when the connection drops in interactive mode
+    #[arg(long, default_value_t = false)]
+    no_reconnect: bool,
+
+    /// Send the command that failed because the connection dropped again after reconnecting
+    #[arg(long, default_value_t = false, conflicts_with = "no_reconnect")]
+    rerun_on_reconnect: bool,
+
+    /// Don't read or save the interactive history file
+    #[arg(long, default_value_t = false)]
+    no_history: bool,
+
+    /// Named server profile from ~/.config/r2con/config.toml, flags given on the command line override it
+    #[arg(long, add = ArgValueCandidates::new(completions::profile_names))]
+    profile: Option<String>,
+
+    /// Run the commands in a file, one per line, after the ones given as arguments (can be repeated)
+    #[arg(short, long)]
+    file: Vec<PathBuf>,
+
     /// commands to run
-    commands:Vec<String>
+    commands:Vec<String>,
+
+    #[command(subcommand)]
+    subcommand: Option<Subcommands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Subcommands {
+    /// Query a Source server with A2S_INFO
+    Query {
+        /// Server address (host[:port], default port 27015)
+        host: String,
+    },
+    /// Check a Minecraft server's status with Server List Ping
+    Ping {
+        /// Server address (host[:port], default port 25565)
+        host: String,
+    },
+    /// Manage the server profiles in ~/.config/r2con/config.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a completion script, e.g. `source <(r2con completions bash)`
+    Completions {
+        shell: Shell,
+    },
+    /// Full screen dashboard with a tab per server profile, output, command line and player counts
+    Tui {
+        /// Profiles to open, all of them when none are given
+        #[arg(add = ArgValueCandidates::new(completions::profile_names))]
+        profiles: Vec<String>,
+    },
+    /// Serve Prometheus metrics (up, auth, round trip time, players) for server profiles
+    Export {
+        /// Address to serve /metrics on
+        #[arg(long, default_value = "0.0.0.0:9123")]
+        listen: SocketAddr,
+        /// Seconds between probes of each server
+        #[arg(long, default_value_t = 15.0, value_parser = parse_seconds)]
+        interval: f64,
+        /// Profiles to export, all of them when none are given
+        #[arg(add = ArgValueCandidates::new(completions::profile_names))]
+        profiles: Vec<String>,
+    },
+    /// HTTP API for sending commands to server profiles: POST /servers/{profile}/command with {"command": "..."}
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+        /// Bearer token clients must send, R2CON_SERVE_TOKEN when not given
+        #[arg(long)]
+        token: Option<String>,
+        /// Profiles to serve, all of them when none are given
+        #[arg(add = ArgValueCandidates::new(completions::profile_names))]
+        profiles: Vec<String>,
+    },
+    /// Send a command over and over to the server given by the usual options and report the round trip times
+    Bench {
+        /// Number of commands to send
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+        /// Connections sending commands at the same time
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        /// Command to send [default: the dialect's player list command, e.g. list]
+        #[arg(long)]
+        command: Option<String>,
+    },
+    /// Send the commands of a session saved with --record again, to the server given by the usual options
+    Replay {
+        /// Recording to replay
+        file: PathBuf,
+        /// Wait as long between commands as in the recording instead of --wait-time
+        #[arg(long, default_value_t = false)]
+        keep_timing: bool,
+    },
 }
 
 struct InputReader<T: AsyncRead> {
@@ -58,7 +356,7 @@ impl<T: AsyncRead + Unpin> InputReader<T> {
         }
     }
 
-    async fn get_input(&mut self, msg: &str) -> Result<String, Box<dyn Error>> {
+    async fn get_input(&mut self, msg: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
         loop {
             let line_opt = self.readline(msg).await?;
             if let Some(line) = line_opt {
@@ -70,138 +368,694 @@ impl<T: AsyncRead + Unpin> InputReader<T> {
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    let cli = Command::new("r2con")
-        .arg(arg!(-p --port <PORT> "RCON port[default: 25575]").value_parser(value_parser!(i32)));
-    let cli = Cli::augment_args(cli);
+    // answers the completion scripts' requests and exits
+    CompleteEnv::with_factory(Cli::command).var(COMPLETE_VAR).complete();
 
-    let matches = cli.get_matches();
+    let matches = match Cli::command().try_get_matches() {
+        Ok(matches) => matches,
+        Err(e) => exit_usage(e),
+    };
 
-    let hostname = get_hostname(matches.get_one::<String>("host").cloned()).await;
-    let password = get_password(matches.get_one::<String>("password").cloned()).await;
-    let port = get_port(matches.get_one::<i32>("port").cloned());
+    // replay goes through the normal connection setup, the other subcommands stand on their own
+    let mut replay = None;
+    let mut bench = None;
+    if matches.subcommand().is_some() {
+        let quiet = matches.get_one::<bool>("quiet").cloned().unwrap();
+        match Subcommands::from_arg_matches(&matches) {
+            Ok(Subcommands::Query { host }) => return query::run_query(&host, quiet).await,
+            Ok(Subcommands::Ping { host }) => return ping::run_ping(&host, quiet, get_timeout(&matches, "connect_timeout")).await,
+            Ok(Subcommands::Config { action }) => return config_cmd::run_config(action, quiet),
+            Ok(Subcommands::Completions { shell }) => return completions::run_completions(shell),
+            Ok(Subcommands::Tui { profiles }) => {
+                return tui::run_tui(profiles, get_timeout(&matches, "connect_timeout"), get_timeout(&matches, "command_timeout")).await
+            }
+            Ok(Subcommands::Export { listen, interval, profiles }) => {
+                let interval = Duration::from_secs_f64(interval.max(1.0));
+                return export::run_export(listen, interval, profiles, get_timeout(&matches, "connect_timeout"), get_timeout(&matches, "command_timeout")).await
+            }
+            Ok(Subcommands::Serve { listen, token, profiles }) => {
+                return serve::run_serve(listen, token, profiles, get_timeout(&matches, "connect_timeout"), get_timeout(&matches, "command_timeout")).await
+            }
+            Ok(Subcommands::Replay { file, keep_timing }) => replay = Some((file, keep_timing)),
+            Ok(Subcommands::Bench { count, concurrency, command }) => bench = Some((count, concurrency, command)),
+            Err(e) => exit_usage(e),
+        }
+    }
+
+    let verbosity = Verbosity::from_flags(matches.get_one::<bool>("quiet").cloned().unwrap(), matches.get_count("verbose"));
+    if verbosity == Verbosity::Debug {
+        tracing_subscriber::fmt().with_max_level(LevelFilter::DEBUG).with_writer(std::io::stderr).init();
+    }
+    let profile_name = match matches.get_one::<String>("profile") {
+        Some(name) => Some(name.clone()),
+        None if !matches.contains_id("host") && !matches.contains_id("hosts") => ConfigFile::load().ok().and_then(|config| config.default),
+        None => None,
+    };
+    let profile = match &profile_name {
+        Some(name) => match ConfigFile::load().and_then(|config| config.profile(name).cloned()) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        },
+        None => Profile::default(),
+    };
+
+    let password_file = matches.get_one::<PathBuf>("password_file").cloned();
+    let password_command = matches.get_one::<String>("password_command").cloned();
+    let password_arg = match (matches.get_one::<String>("password").cloned(), password_file, password_command) {
+        (Some(password), _, _) => Some(password),
+        (None, Some(path), _) => match read_password_file(&path) {
+            Ok(password) => Some(password),
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        },
+        (None, None, Some(command)) => match run_password_command(&command) {
+            Ok(password) => Some(password),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        },
+        (None, None, None) => match profile.get_password(profile_name.as_deref().unwrap_or_default()) {
+            Ok(password) => password,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        },
+    };
 
     let commands = matches.get_many::<String>("commands");
-    let commands = if let Some(commands) = commands {
+    let mut commands = if let Some(commands) = commands {
         commands.cloned().collect::<Vec<String>>()
     } else {
         Vec::new()
     };
+    // only scripts and replays can pause, a typed or piped `sleep` is sent like any other command
+    let mut pauses = Pauses::new();
+    if let Some((path, keep_timing)) = &replay {
+        match recording::load(path) {
+            Ok(entries) => {
+                for (gap, command) in recording::replay_commands(&entries, *keep_timing) {
+                    if let Some(gap) = gap {
+                        add_pause(&mut pauses, commands.len(), gap.as_secs_f64());
+                    }
+                    commands.push(command);
+                }
+            }
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+    for path in matches.get_many::<PathBuf>("file").into_iter().flatten() {
+        match read_command_file(path) {
+            Ok(lines) => {
+                for line in lines {
+                    match parse_sleep(&line) {
+                        Some(Ok(seconds)) => add_pause(&mut pauses, commands.len(), seconds),
+                        Some(Err(e)) => {
+                            eprintln!("error: {}: {}", path.display(), e);
+                            return ExitCode::from(EXIT_USAGE);
+                        }
+                        None => commands.push(line),
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
 
-    let silent = matches.get_one::<bool>("silent").cloned().unwrap();
-    let wait_time = matches.get_one::<f64>("wait_time").cloned().unwrap();
-    let mut interactive = matches.get_one::<bool>("interactive").cloned().unwrap();
+    // `generate-commands.sh | r2con -H host` runs what the pipe produces instead of prompting
+    let interactive_flag = matches.get_one::<bool>("interactive").cloned().unwrap();
+    let commands_from_stdin = commands.is_empty() && pauses.is_empty() && !interactive_flag && bench.is_none() && !std::io::stdin().is_terminal();
+    if commands_from_stdin {
+        let mut input = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut input).await {
+            eprintln!("error: could not read commands from stdin: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+        commands = parse_commands(&input);
+    }
 
-    let addr = if let Ok(hostname) = hostname {
-        hostname + ":" + &port.to_string()
-    } else {
-        if !silent {
-            eprintln!("error: no hostname could be read");
+    let config_file = ConfigFile::load().unwrap_or_default();
+    let variables: BTreeMap<String, String> = matches.get_many::<(String, String)>("var").into_iter().flatten().cloned().collect();
+    for command in commands.iter_mut() {
+        match expand_command(&config_file.aliases, &variables, command) {
+            Ok(expanded) => *command = expanded,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let watch_options = match matches.get_one::<f64>("watch").cloned() {
+        Some(_) if commands.is_empty() => {
+            eprintln!("error: --watch needs at least one command");
+            return ExitCode::from(EXIT_USAGE);
+        }
+        Some(seconds) if seconds <= 0.0 => {
+            eprintln!("error: the --watch interval must be greater than 0");
+            return ExitCode::from(EXIT_USAGE);
+        }
+        Some(seconds) => Some(WatchOptions {
+            interval: Duration::from_secs_f64(seconds),
+            clear: matches.get_one::<bool>("clear").cloned().unwrap(),
+            diff: matches.get_one::<bool>("diff").cloned().unwrap(),
+        }),
+        None => None,
+    };
+
+    let mut hosts: Vec<String> = matches.get_many::<String>("host").into_iter().flatten().cloned().collect();
+    hosts.extend(matches.get_many::<String>("hosts").into_iter().flatten().cloned());
+    if hosts.is_empty() {
+        match get_hostname(profile.host.clone(), !commands_from_stdin).await {
+            Ok(hostname) => hosts.push(hostname),
+            Err(_) => {
+                eprintln!("error: no hostname could be read");
+                return ExitCode::from(EXIT_USAGE);
+            }
         }
-        return ExitCode::FAILURE;
+    }
+    let mut specs = Vec::new();
+    for host in &hosts {
+        match HostSpec::parse(host) {
+            Ok(spec) => specs.push(spec),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+    let dry_run = matches.get_one::<bool>("dry_run").cloned().unwrap();
+    // rcon:// URLs can bring their own password
+    let password_needed = specs.iter().any(|spec| spec.password.is_none());
+    let password_given = password_arg.is_some() || !password_needed;
+    let password = match dry_run || !password_needed {
+        // nothing is sent so there is no need to ask
+        true => Ok(String::new()),
+        false => get_password(password_arg, commands_from_stdin).await,
     };
+    let protocol = matches.get_one::<Protocol>("protocol").cloned().unwrap();
+    let port_arg = matches.get_one::<u16>("port").cloned().or(profile.port);
+    let port = get_port(port_arg, protocol.default_port());
+
+    let wait_time = match matches.value_source("wait_time") {
+        Some(ValueSource::CommandLine) => matches.get_one::<f64>("wait_time").cloned().unwrap(),
+        _ => profile.wait_time.unwrap_or(matches.get_one::<f64>("wait_time").cloned().unwrap()),
+    };
+    // the command line is checked by clap, a hand-edited profile isn't
+    if Duration::try_from_secs_f64(wait_time).is_err() {
+        eprintln!("error: the profile's wait_time must be a finite number of seconds that isn't negative");
+        return ExitCode::from(EXIT_USAGE);
+    }
+    let proxy = matches.get_one::<String>("proxy").cloned();
+    let encoding = matches.get_one::<TextEncoding>("encoding").cloned().unwrap();
+    let encoding_given = matches.value_source("encoding") == Some(ValueSource::CommandLine);
+    let srv = matches.get_one::<bool>("srv").cloned().unwrap();
+    let dialect = match matches.value_source("dialect") {
+        Some(ValueSource::CommandLine) => matches.get_one::<GameDialect>("dialect").cloned().unwrap(),
+        _ => profile.dialect.unwrap_or(matches.get_one::<GameDialect>("dialect").cloned().unwrap()),
+    };
+
+    let mut targets = Vec::new();
+    for spec in specs {
+        let addr = match spec.port {
+            Some(host_port) => spec.addr(host_port),
+            None if srv && port_arg.is_none() => match resolve_minecraft_srv(&spec.hostname).await {
+                Ok(Some((host, port))) => host + ":" + &port.to_string(),
+                Ok(None) => spec.addr(port),
+                Err(e) => {
+                    eprintln!("error: SRV lookup failed: {}", e);
+                    return ExitCode::from(EXIT_CONNECTION);
+                }
+            },
+            None => spec.addr(port),
+        };
+        targets.push(HostTarget { label: spec.label(), addr, password: spec.password });
+    }
+    let multiple_targets = targets.len() > 1;
+    if multiple_targets && (interactive_flag || watch_options.is_some() || bench.is_some()) {
+        eprintln!("error: --interactive, --watch and bench can only be used with a single host");
+        return ExitCode::from(EXIT_USAGE);
+    }
+    if multiple_targets && commands.is_empty() {
+        eprintln!("error: no commands to run on the hosts");
+        return ExitCode::from(EXIT_USAGE);
+    }
+    if dry_run {
+        return dry_run::run(&dry_run::Plan {
+            profile: profile_name.as_deref(),
+            targets: &targets,
+            protocol,
+            dialect,
+            password_given: password_given || env::var("R2CON_PASS").is_ok(),
+            commands: &commands,
+            hexdump: matches.get_one::<bool>("hexdump").cloned().unwrap(),
+        });
+    }
 
     let password = if let Ok(password) = password {
         password
     } else {
-        if !silent {
-            eprintln!("error: no password could be read");
-        }
-        return ExitCode::FAILURE;
+        eprintln!("error: no password could be read");
+        return ExitCode::from(EXIT_USAGE);
     };
 
-    let client = RconClient::connect(addr, &password).await;
+    let history = match matches.get_one::<bool>("no_history").cloned().unwrap() {
+        true => HistorySettings { enabled: false, ..HistorySettings::default() },
+        false => config_file.history.clone(),
+    };
+    let prompt = matches.get_one::<String>("prompt").cloned()
+        .or(profile.prompt.clone())
+        .or(config_file.prompt.clone())
+        .unwrap_or(DEFAULT_PROMPT.to_string());
 
-    return match client {
-        Ok(mut rcon_client) => {
-            if commands.is_empty() {
-                interactive = true;
+    let json = matches.get_one::<bool>("json").cloned().unwrap();
+    let color = match matches.get_one::<ColorChoice>("color").cloned().unwrap() {
+        // escapes inside JSON strings are never wanted unless asked for
+        ColorChoice::Auto if json => ColorMode::Strip,
+        color => color.resolve(),
+    };
+    let files = match matches.get_one::<PathBuf>("output_dir") {
+        Some(dir) => match OutputFiles::new(dir.clone(), matches.get_one::<String>("output_template").cloned().unwrap()) {
+            Ok(files) if multiple_targets => Some(files),
+            Ok(files) => Some(files.for_host(&targets[0].label, false)),
+            Err(e) => {
+                eprintln!("error: could not create {}: {}", dir.display(), e);
+                return ExitCode::from(EXIT_USAGE);
             }
-            let command_loop_result = command_loop(&mut rcon_client, &commands, silent, wait_time).await;
-            let result = if let Ok(_) = command_loop_result {
-                if interactive {
-                    if !silent {
-                        if let Ok(addr) = rcon_client.get_address() {
-                            println!("Connected to {}", addr.to_string());
-                        }
-                        println!("Type 'quit' to close.");
-                    }
-                    interactive_command_loop(&mut rcon_client, silent).await 
-                } else {
-                    command_loop_result
-                }
-            } else {
-                command_loop_result
-            };
-            
-            if let Err(_) = result {
-                ExitCode::FAILURE
-            } else {
-                ExitCode::SUCCESS
+        },
+        None => None,
+    };
+    let rcon_dialect = match protocol {
+        Protocol::Rcon => Some(dialect),
+        _ => None,
+    };
+    let repl_options = ReplOptions {
+        output: OutputOptions {
+            verbosity,
+            color,
+            json,
+            raw: matches.get_one::<bool>("raw").cloned().unwrap(),
+            files,
+            format: matches.get_one::<OutputFormat>("format").cloned().unwrap(),
+            dialect: rcon_dialect,
+            grep: matches.get_one::<Regex>("grep").cloned(),
+            invert: matches.get_one::<bool>("invert").cloned().unwrap(),
+            host: None,
+            captured: None,
+        },
+        history,
+        dialect: rcon_dialect,
+        prompt,
+        profile: profile_name.clone(),
+        aliases: config_file.aliases.clone(),
+        variables,
+        auto_reconnect: !matches.get_one::<bool>("no_reconnect").cloned().unwrap(),
+        reconnect_delay: Duration::from_secs_f64(matches.get_one::<f64>("retry_delay").cloned().unwrap()),
+        rerun_on_reconnect: matches.get_one::<bool>("rerun_on_reconnect").cloned().unwrap(),
+    };
+
+    if protocol != Protocol::Rcon && proxy.is_some() {
+        eprintln!("error: --proxy is only supported with the rcon protocol");
+        return ExitCode::from(EXIT_USAGE);
+    }
+    let log = match matches.get_one::<PathBuf>("log_file") {
+        Some(path) => match SessionLog::open(path) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("error: could not open {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_USAGE);
             }
+        },
+        None => None,
+    };
+    let recorder = match matches.get_one::<PathBuf>("record") {
+        Some(path) => match Recorder::create(path) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                eprintln!("error: could not create {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        },
+        None => None,
+    };
+    let mut rcon_config = RconConfig::new().dialect(dialect.into());
+    if encoding_given {
+        rcon_config = rcon_config.encoding(encoding.into());
+    }
+    if let Some(proxy) = proxy {
+        rcon_config = rcon_config.proxy(get_proxy_config(&proxy));
+    }
+    let session = Session {
+        protocol,
+        rcon_config,
+        password,
+        connect_timeout: get_timeout(&matches, "connect_timeout"),
+        command_timeout: get_timeout(&matches, "command_timeout"),
+        retries: matches.get_one::<u32>("retries").cloned().unwrap(),
+        retry_delay: matches.get_one::<f64>("retry_delay").cloned().unwrap(),
+        // with nothing to run the prompt opens right away
+        interactive: interactive_flag || (commands.is_empty() && pauses.is_empty() && !commands_from_stdin),
+        commands,
+        pauses,
+        wait_time,
+        wait_jitter: matches.get_one::<f64>("wait_jitter").cloned().unwrap(),
+        keep_going: matches.get_one::<bool>("keep_going").cloned().unwrap(),
+        expectations: Expectations {
+            all: matches.get_one::<Regex>("expect").cloned(),
+            each: matches.get_many::<Regex>("expect_each").into_iter().flatten().cloned().collect(),
+        },
+        watch: watch_options,
+        repl_options,
+        log,
+        recorder,
+        shutdown: Shutdown::listen(),
+    };
+
+    if let Some((count, concurrency, command)) = bench {
+        let options = BenchOptions {
+            count: count.max(1),
+            concurrency: concurrency.max(1),
+            command: command.unwrap_or_else(|| dialect.players_command().to_string()),
+        };
+        let result = bench::run(&session.target(&targets[0]), session.command_timeout, &options, &session.repl_options.output).await;
+        return get_exit_code(&result);
+    }
+
+    let host_output = |host: &str| {
+        let mut output = session.repl_options.output.clone();
+        if multiple_targets {
+            output.host = Some(host.to_string());
+            output.files = output.files.as_ref().map(|files| files.for_host(host, true));
+        }
+        output
+    };
+    let results = match matches.get_one::<usize>("parallel").cloned() {
+        Some(limit) if multiple_targets => {
+            stream::iter(&targets)
+                .map(|target| run_host(&session, target, host_output(&target.label).capture()))
+                .buffer_unordered(limit.max(1))
+                .collect::<Vec<_>>()
+                .await
+        }
+        _ => {
+            let mut results = Vec::new();
+            for target in &targets {
+                results.push(run_host(&session, target, host_output(&target.label)).await);
+            }
+            results
+        }
+    };
+
+    if multiple_targets {
+        let (succeeded, failed): (Vec<_>, Vec<_>) = results.iter().partition(|(_, result)| result.is_ok());
+        let succeeded: Vec<String> = succeeded.into_iter().map(|(host, _)| host.clone()).collect();
+        let failed: Vec<String> = failed.into_iter().map(|(host, _)| host.clone()).collect();
+        session.repl_options.output.print_summary(&succeeded, &failed);
+    }
+    // the first failure decides the exit code, the other hosts still get the commands
+    match results.iter().find(|(_, result)| result.is_err()) {
+        Some((_, result)) => get_exit_code(result),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+// everything needed to connect to a server and run the commands, shared by every target
+struct Session {
+    protocol: Protocol,
+    rcon_config: RconConfig,
+    password: String,
+    connect_timeout: Option<Duration>,
+    command_timeout: Option<Duration>,
+    retries: u32,
+    retry_delay: f64,
+    commands: Vec<String>,
+    pauses: Pauses,
+    wait_time: f64,
+    wait_jitter: f64,
+    keep_going: bool,
+    expectations: Expectations,
+    interactive: bool,
+    watch: Option<WatchOptions>,
+    repl_options: ReplOptions,
+    log: Option<SessionLog>,
+    recorder: Option<Recorder>,
+    shutdown: Shutdown,
+}
+
+impl Session {
+    fn target(&self, host: &HostTarget) -> Target {
+        Target {
+            protocol: self.protocol,
+            rcon_config: self.rcon_config.clone(),
+            addr: host.addr.clone(),
+            password: host.password.clone().unwrap_or_else(|| self.password.clone()),
+            connect_timeout: self.connect_timeout,
         }
-        Err(ref e) if e.is::<RconAuthError>() => {
-            if !silent {
-                eprintln!("wrong password: {}", e);
+    }
+}
+
+// errors are printed here, the result only tells the caller how it went
+async fn run_target(session: &Session, host: &HostTarget, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let addr = host.addr.as_str();
+    let target = session.target(host);
+    output.print_info(Verbosity::Verbose, &format!("Connecting to {} over {:?}", addr, session.protocol));
+    let mut attempt = 0;
+    let connect_loop = async {
+        loop {
+            match target.connect().await {
+                // a wrong password won't fix itself
+                Err(e) if attempt < session.retries && !e.is::<RconAuthError>() => {
+                    attempt += 1;
+                    output.print_info(Verbosity::Normal, &format!("Connection failed: {}, retrying in {}s ({}/{})", e, session.retry_delay, attempt, session.retries));
+                    sleep(Duration::from_secs_f64(session.retry_delay)).await;
+                }
+                result => break result,
             }
-            ExitCode::FAILURE
+        }
+    };
+    // nothing to close yet, the connection attempt is just dropped
+    let client = tokio::select! {
+        result = connect_loop => result,
+        terminated = session.shutdown.terminated() => {
+            output.print_error("terminated", &terminated.to_string());
+            return Err(terminated.into());
+        }
+    };
+
+    let mut rcon_client = match client {
+        Ok(client) => {
+            output.print_info(Verbosity::Verbose, &format!("Logged in to {}", addr));
+            let log = session.log.as_ref().map(|log| log.for_host(addr));
+            let recorder = session.recorder.as_ref().map(|recorder| recorder.for_host(addr));
+            Connection::new(target, client, session.command_timeout, log, recorder)
         }
         Err(e) => {
-            if !silent {
-                eprintln!("connection Error: {}", e);
+            if is_timeout(e.as_ref()) {
+                let seconds = session.connect_timeout.unwrap_or_default().as_secs_f64();
+                output.print_error("timeout", &format!("connection Error: no response after {}s", seconds));
+            } else if e.is::<RconAuthError>() {
+                output.print_error("auth", &format!("wrong password: {}", e));
+            } else {
+                output.print_error("connection", &format!("connection Error: {}", e));
             }
-            ExitCode::FAILURE
+            return Err(e);
         }
     };
+
+    let work = async {
+        if let Some(watch_options) = &session.watch {
+            return watch::run(&mut rcon_client, &session.commands, &session.pauses, watch_options, output).await;
+        }
+        command_loop(&mut rcon_client, session, output).await?;
+        if session.interactive {
+            if !output.verbosity.is_quiet() {
+                if let Some(addr) = rcon_client.get_address() {
+                    println!("Connected to {}", addr);
+                }
+                println!("Type 'quit' to close or ':help' for client commands.");
+            }
+            return repl::run(&mut rcon_client, &session.repl_options).await;
+        }
+        Ok(())
+    };
+    // the command in flight is abandoned, the connection is still closed properly
+    let result = tokio::select! {
+        result = work => result,
+        terminated = session.shutdown.terminated() => {
+            output.print_error("terminated", &terminated.to_string());
+            Err(terminated.into())
+        }
+    };
+
+    if result.is_ok() || matches!(&result, Err(e) if e.is::<Terminated>()) {
+        output.print_info(Verbosity::Verbose, &format!("Closing the connection to {}", addr));
+        let _ = rcon_client.close().await;
+    }
+    result
+}
+
+async fn run_host(session: &Session, host: &HostTarget, output: OutputOptions) -> (String, Result<(), Box<dyn Error + Send + Sync>>) {
+    let result = run_target(session, host, &output).await;
+    output.flush_captured();
+    (host.label.clone(), result)
+}
+
+fn get_exit_code(result: &Result<(), Box<dyn Error + Send + Sync>>) -> ExitCode {
+    match result {
+        Err(e) if e.is::<Terminated>() => ExitCode::from(e.downcast_ref::<Terminated>().unwrap().exit_code()),
+        Err(e) if e.is::<RconAuthError>() => ExitCode::from(EXIT_AUTH),
+        Err(e) if e.is::<ConnectError>() || is_timeout(e.as_ref()) || is_disconnect(e.as_ref()) => ExitCode::from(EXIT_CONNECTION),
+        // the server was reached, a command failed, didn't match --expect or its response couldn't be saved
+        Err(_) => ExitCode::from(EXIT_COMMAND),
+        Ok(_) => ExitCode::SUCCESS,
+    }
+}
+
+// clap exits with 2 for usage errors, which is taken by auth failures here
+fn exit_usage(e: clap::Error) -> ! {
+    if !e.use_stderr() {
+        // --help and --version
+        e.exit();
+    }
+    let _ = e.print();
+    std::process::exit(EXIT_USAGE as i32);
 }
 
-async fn run_command(rcon_client: &mut RconClient, command:&str, silent:bool)-> Result<(), Box<dyn Error>>{
+async fn run_command(rcon_client: &mut Connection, command:&str, output_options:&OutputOptions)-> Result<String, Box<dyn Error + Send + Sync>>{
+    let start = Instant::now();
+    if output_options.raw {
+        return match rcon_client.send_command_raw(command).await {
+            Ok(bytes) => {
+                output_options.print_raw_response(&bytes);
+                write_output_file(output_options, command, &bytes, true)?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Err(e) => {
+                output_options.print_command_error(command, &e.to_string(), start.elapsed());
+                Err(e)
+            }
+        };
+    }
     let result = rcon_client.send_command(command).await;
     match result {
         Ok(output) => {
-            if !output.is_empty() && !silent {
-                println!("{}", output);
-            }
+            output_options.print_response(command, &output, start.elapsed());
+            write_output_file(output_options, command, output.as_bytes(), false)?;
+            Ok(output)
         }
         Err(e) => {
-            if !silent {
-                eprintln!("An error occured while sending the command:");
-                eprintln!("Error: {}", e);
-            }
-            return Err(e.into());
+            output_options.print_command_error(command, &e.to_string(), start.elapsed());
+            Err(e)
         }
     }
+}
+
+// a response that couldn't be saved fails the command, scripts rely on the files being there
+fn write_output_file(output_options: &OutputOptions, command: &str, output: &[u8], raw: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let files = match &output_options.files {
+        Some(files) => files,
+        None => return Ok(()),
+    };
+    if let Err(e) = files.write(command, output, raw) {
+        output_options.print_error("output", &format!("could not write the response of '{}': {}", command, e));
+        return Err(e.into());
+    }
     Ok(())
 }
 
-async fn command_loop(rcon_client: &mut RconClient, commands: &Vec<String>, silent:bool, wait_time:f64) -> Result<(), Box<dyn Error>>{
-    let command_count = commands.len();
-    for (i, command) in commands.iter().enumerate() {
-        if let Err(e) = run_command(rcon_client, command, silent).await {
-            return Err(e);
+// stops at the first failing command unless keep_going is set, then the first error is returned at the end
+async fn command_loop(rcon_client: &mut Connection, session:&Session, output_options:&OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>>{
+    let mut command_count = 0;
+    let mut first_error = None;
+    let mut failed = Vec::new();
+    // nothing to wait for before the first command or right after a sleep line
+    let mut waited = true;
+    for (index, command) in session.commands.iter().enumerate() {
+        if let Some(seconds) = session.pauses.get(&index) {
+            sleep(jittered(*seconds, session.wait_jitter)).await;
+            waited = true;
         }
-        if i != command_count-1 {
-            sleep(Duration::from_secs_f64(wait_time)).await;
+        if !waited {
+            sleep(jittered(session.wait_time, session.wait_jitter)).await;
+        }
+        waited = false;
+        command_count += 1;
+        let result: Result<(), Box<dyn Error + Send + Sync>> = match run_command(rcon_client, command, output_options).await {
+            Ok(output) => session.expectations.check(index, command, &output).map_err(|e| {
+                output_options.print_error("expect", &e.to_string());
+                e.into()
+            }),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if !session.keep_going {
+                return Err(e);
+            }
+            failed.push(command.as_str());
+            first_error.get_or_insert(e);
         }
     }
-    Ok(())
+    // a sleep at the end of a script still keeps the connection open that long
+    if let Some(seconds) = session.pauses.get(&session.commands.len()) {
+        sleep(jittered(*seconds, session.wait_jitter)).await;
+    }
+    match first_error {
+        Some(e) => {
+            output_options.print_error("commands", &format!("{} of {} commands failed: {}", failed.len(), command_count, failed.join(", ")));
+            Err(e)
+        }
+        None => Ok(()),
+    }
 }
 
-async fn interactive_command_loop(rcon_client: &mut RconClient, silent:bool) -> Result<(), Box<dyn Error>> {
-    let stdin = io::stdin();
-    let mut reader = InputReader::new(stdin);
-    loop {
-        let line = reader.get_input("> ").await?;
-        let trimmed_line = line.trim();
-        if trimmed_line == "quit" {
-            break;
-        } else if !trimmed_line.is_empty() {
-            run_command(rcon_client, trimmed_line, silent).await?
-        }
+// aliases first so their templates can use variables too
+fn expand_command(aliases: &BTreeMap<String, String>, variables: &BTreeMap<String, String>, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let command = alias::expand(aliases, command)?;
+    Ok(vars::expand(variables, &command)?)
+}
+
+// seconds to wait before the command at an index, the length of the commands for after the last one
+type Pauses = BTreeMap<usize, f64>;
+// a day, longer waits are almost certainly a typo
+const MAX_SLEEP: f64 = 86400.0;
+
+// `sleep 3` in a script waits 3 seconds instead of --wait-time, it is never sent to the server,
+// a number that can't be waited for is an error rather than a command
+fn parse_sleep(command: &str) -> Option<Result<f64, String>> {
+    let seconds = match command.split_once(' ') {
+        Some(("sleep", seconds)) => seconds.trim().parse::<f64>().ok()?,
+        _ => return None,
+    };
+    match (0.0..=MAX_SLEEP).contains(&seconds) {
+        true => Some(Ok(seconds)),
+        false => Some(Err(format!("'{}' must wait between 0 and {} seconds", command, MAX_SLEEP))),
     }
-    Ok(())
 }
 
-async fn get_hostname(arg: Option<String>) -> Result<String, Box<dyn Error>> {
+// sleep lines in a row add up
+fn add_pause(pauses: &mut Pauses, index: usize, seconds: f64) {
+    *pauses.entry(index).or_insert(0.0) += seconds;
+}
+
+// adds up to jitter seconds so servers restarted by the same script don't all wait the same time
+fn jittered(seconds: f64, jitter: f64) -> Duration {
+    let extra = if jitter > 0.0 { rand::thread_rng().gen_range(0.0..jitter) } else { 0.0 };
+    Duration::try_from_secs_f64(seconds + extra).unwrap_or(Duration::MAX)
+}
+
+async fn get_hostname(arg: Option<String>, can_prompt: bool) -> Result<String, Box<dyn Error + Send + Sync>> {
     let stdin = io::stdin();
     let mut reader = InputReader::new(stdin);
     if let Some(hostname) = arg {
@@ -209,29 +1063,81 @@ async fn get_hostname(arg: Option<String>) -> Result<String, Box<dyn Error>> {
     } else {
         if let Ok(hostname) = env::var("R2CON_HOST") {
             Ok(hostname)
+        } else if !can_prompt {
+            Err("stdin is used for commands".into())
         } else {
             reader.get_input("Hostname: ").await
         }
     }
 }
 
-fn get_port(arg: Option<i32>) -> i32 {
+// anything Duration can't hold would panic where the value is used, clap turns the error into a usage error
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let seconds = value.parse::<f64>().map_err(|e| e.to_string())?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(_) => Ok(seconds),
+        Err(_) => Err("expected a finite number of seconds that isn't negative".to_string()),
+    }
+}
+
+// 0 disables the timeout
+fn get_timeout(matches: &ArgMatches, id: &str) -> Option<Duration> {
+    match matches.get_one::<f64>(id).cloned() {
+        Some(seconds) if seconds > 0.0 => Some(Duration::from_secs_f64(seconds)),
+        _ => None,
+    }
+}
+
+fn get_port(arg: Option<u16>, default_port: u16) -> u16 {
     if let Some(port) = arg {
         port
     } else {
         if let Ok(port_str) = env::var("R2CON_PORT") {
-            if let Ok(port) = port_str.parse::<i32>() {
+            if let Ok(port) = port_str.parse::<u16>() {
                 port
             } else {
-                DEFAULT_PORT
+                default_port
             }
         } else {
-            DEFAULT_PORT
+            default_port
         }
     }
 }
 
-async fn get_password(arg: Option<String>) -> Result<String, Box<dyn Error>> {
+fn get_proxy_config(arg: &str) -> Socks5Config {
+    if let Some((credentials, addr)) = arg.rsplit_once('@') {
+        let proxy = Socks5Config::new(addr);
+        if let Some((username, password)) = credentials.split_once(':') {
+            proxy.credentials(username, password)
+        } else {
+            proxy.credentials(credentials, "")
+        }
+    } else {
+        Socks5Config::new(arg)
+    }
+}
+
+// one command per line, blank lines and lines starting with # are skipped
+fn read_command_file(path: &Path) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(parse_commands(&contents))
+}
+
+fn parse_commands(contents: &str) -> Vec<String> {
+    contents.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn read_password_file(path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents.lines().next().unwrap_or("").trim().to_string())
+}
+
+// when stdin carries the commands the prompt reads from the terminal directly
+async fn get_password(arg: Option<String>, stdin_taken: bool) -> Result<String, Box<dyn Error + Send + Sync>> {
     let stdin = io::stdin();
     let mut reader = InputReader::new(stdin);
     if let Some(password) = arg {
@@ -239,6 +1145,10 @@ async fn get_password(arg: Option<String>) -> Result<String, Box<dyn Error>> {
     } else {
         if let Ok(hostname) = env::var("R2CON_PASS") {
             Ok(hostname)
+        } else if std::io::stdin().is_terminal() || stdin_taken {
+            // read without echoing, piped passwords keep going through the reader
+            let password = tokio::task::spawn_blocking(|| rpassword::prompt_password("Password: ")).await??;
+            Ok(password)
         } else {
             reader.get_input("Password: ").await
         }
@@ -0,0 +1,274 @@
+use std::{env, io::{self, IsTerminal, Write}, sync::{Arc, Mutex}, time::Duration};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Serialize;
+
+use r2con::ColorMode;
+
+use crate::{connection::GameDialect, output_files::OutputFiles, table::{self, OutputFormat}};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// Colors when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    /// Always convert formatting codes to ANSI colors
+    Always,
+    /// Always strip formatting codes
+    Never,
+}
+
+impl ColorChoice {
+    pub fn resolve(&self) -> ColorMode {
+        match self {
+            ColorChoice::Always => ColorMode::Ansi,
+            ColorChoice::Never => ColorMode::Strip,
+            ColorChoice::Auto if io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none() => ColorMode::Ansi,
+            ColorChoice::Auto => ColorMode::Strip,
+        }
+    }
+}
+
+// -q, nothing, -v and -vv
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    // only errors
+    Quiet,
+    Normal,
+    // connection lifecycle
+    Verbose,
+    // packets as they are sent and received
+    Debug,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: u8) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        *self == Verbosity::Quiet
+    }
+}
+
+#[derive(Serialize)]
+struct CommandRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<&'a str>,
+    command: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    success: bool,
+    duration_ms: u128,
+}
+
+#[derive(Serialize)]
+struct SummaryRecord<'a> {
+    succeeded: &'a [String],
+    failed: &'a [String],
+}
+
+#[derive(Clone, Debug)]
+pub enum Line {
+    Stdout(String),
+    Stderr(String),
+    // --raw responses, written out without a newline
+    Raw(Vec<u8>),
+}
+
+#[derive(Serialize)]
+struct InfoRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<&'a str>,
+    info: &'a str,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<&'a str>,
+    error: &'a str,
+    kind: &'a str,
+}
+
+// how responses are printed, shared by the command loop and interactive mode
+#[derive(Clone, Debug)]
+pub struct OutputOptions {
+    pub verbosity: Verbosity,
+    pub color: ColorMode,
+    // one JSON object per command on stdout, errors as JSON on stderr
+    pub json: bool,
+    // responses are written byte for byte, without decoding, color handling or a trailing newline
+    pub raw: bool,
+    // --output-dir, responses also written to a file each
+    pub files: Option<OutputFiles>,
+    // --format, list outputs are parsed with the dialect's parsers
+    pub format: OutputFormat,
+    pub dialect: Option<GameDialect>,
+    // --grep, only matching lines of a response are printed, or only the others with --invert
+    pub grep: Option<Regex>,
+    pub invert: bool,
+    // set when running on several servers, every line is prefixed with it
+    pub host: Option<String>,
+    // lines held back until the host is done so parallel runs don't interleave
+    pub captured: Option<Arc<Mutex<Vec<Line>>>>,
+}
+
+impl OutputOptions {
+    pub fn print_response(&self, command: &str, output: &str, duration: Duration) {
+        if self.verbosity.is_quiet() {
+            return;
+        }
+        let filtered = self.filter_lines(output);
+        let output = filtered.as_deref().unwrap_or(output);
+        if self.json {
+            let record = CommandRecord {
+                host: self.host.as_deref(),
+                command,
+                output: Some(self.color.apply(output)),
+                error: None,
+                success: true,
+                duration_ms: duration.as_millis(),
+            };
+            self.stdout(serde_json::to_string(&record).unwrap_or_default());
+        } else if let Some(table) = self.format_table(command, output) {
+            self.stdout(self.prefix_lines(&table));
+        } else if !output.is_empty() {
+            self.stdout(self.prefix_lines(&self.color.apply(output)));
+        }
+    }
+
+    // lines are matched without formatting codes but kept with them
+    fn filter_lines(&self, output: &str) -> Option<String> {
+        let grep = self.grep.as_ref()?;
+        let lines: Vec<&str> = output.lines().filter(|line| grep.is_match(&ColorMode::Strip.apply(line)) != self.invert).collect();
+        Some(lines.join("\n"))
+    }
+
+    // outputs that aren't a known list are printed as text
+    fn format_table(&self, command: &str, output: &str) -> Option<String> {
+        if self.format == OutputFormat::Text {
+            return None;
+        }
+        let table = table::parse(self.dialect?, command, &ColorMode::Strip.apply(output))?;
+        Some(table.render(self.format))
+    }
+
+    // no host prefix either, the bytes are exactly what the server sent
+    pub fn print_raw_response(&self, output: &[u8]) {
+        if self.verbosity.is_quiet() {
+            return;
+        }
+        match &self.captured {
+            Some(captured) => captured.lock().unwrap().push(Line::Raw(output.to_vec())),
+            None => write_raw(output),
+        }
+    }
+
+    // errors are printed at every verbosity
+    pub fn print_command_error(&self, command: &str, error: &str, duration: Duration) {
+        if self.json {
+            let record = CommandRecord {
+                host: self.host.as_deref(),
+                command,
+                output: None,
+                error: Some(error.to_string()),
+                success: false,
+                duration_ms: duration.as_millis(),
+            };
+            self.stderr(serde_json::to_string(&record).unwrap_or_default());
+        } else {
+            self.stderr(self.prefix_lines("An error occured while sending the command:"));
+            self.stderr(self.prefix_lines(&format!("Error: {}", error)));
+        }
+    }
+
+    // errors outside of a command, kind is a short machine readable category like "auth"
+    pub fn print_error(&self, kind: &str, message: &str) {
+        if self.json {
+            let record = ErrorRecord { host: self.host.as_deref(), error: message, kind };
+            self.stderr(serde_json::to_string(&record).unwrap_or_default());
+        } else {
+            self.stderr(self.prefix_lines(message));
+        }
+    }
+
+    // progress and lifecycle messages, shown when the verbosity is at least level
+    pub fn print_info(&self, level: Verbosity, message: &str) {
+        if self.verbosity.is_quiet() || self.verbosity < level {
+            return;
+        }
+        if self.json {
+            let record = InfoRecord { host: self.host.as_deref(), info: message };
+            self.stderr(serde_json::to_string(&record).unwrap_or_default());
+        } else {
+            self.stderr(self.prefix_lines(message));
+        }
+    }
+
+    pub fn print_summary(&self, succeeded: &[String], failed: &[String]) {
+        if self.verbosity.is_quiet() && failed.is_empty() {
+            return;
+        }
+        if self.json {
+            let record = SummaryRecord { succeeded, failed };
+            println!("{}", serde_json::to_string(&record).unwrap_or_default());
+        } else if failed.is_empty() {
+            eprintln!("{} hosts succeeded", succeeded.len());
+        } else {
+            eprintln!("{} hosts succeeded, {} failed: {}", succeeded.len(), failed.len(), failed.join(", "));
+        }
+    }
+
+    // a copy for one host that keeps its lines until flush_captured
+    pub fn capture(&self) -> OutputOptions {
+        OutputOptions { captured: Some(Arc::new(Mutex::new(Vec::new()))), ..self.clone() }
+    }
+
+    pub fn flush_captured(&self) {
+        let lines = match &self.captured {
+            Some(captured) => std::mem::take(&mut *captured.lock().unwrap()),
+            None => return,
+        };
+        for line in lines {
+            match line {
+                Line::Stdout(text) => println!("{}", text),
+                Line::Stderr(text) => eprintln!("{}", text),
+                Line::Raw(bytes) => write_raw(&bytes),
+            }
+        }
+    }
+
+    fn stdout(&self, text: String) {
+        match &self.captured {
+            Some(captured) => captured.lock().unwrap().push(Line::Stdout(text)),
+            None => println!("{}", text),
+        }
+    }
+
+    fn stderr(&self, text: String) {
+        match &self.captured {
+            Some(captured) => captured.lock().unwrap().push(Line::Stderr(text)),
+            None => eprintln!("{}", text),
+        }
+    }
+
+    fn prefix_lines(&self, text: &str) -> String {
+        match &self.host {
+            Some(host) => text.lines().map(|line| format!("[{}] {}", host, line)).collect::<Vec<String>>().join("\n"),
+            None => text.to_string(),
+        }
+    }
+}
+
+fn write_raw(bytes: &[u8]) {
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(bytes);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,77 @@
+use std::{fs, io, path::PathBuf, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
+
+use r2con::ColorMode;
+
+pub const DEFAULT_TEMPLATE: &str = "{index}-{command}.txt";
+
+// longer commands are cut in file names, the index keeps them apart
+const MAX_COMMAND_LENGTH: usize = 64;
+
+// --output-dir: every response also goes to a file of its own
+#[derive(Clone, Debug)]
+pub struct OutputFiles {
+    dir: PathBuf,
+    // {index}, {command} and {host} are replaced
+    template: String,
+    host: String,
+    // commands sent so far, shared by the clones handed to the REPL and the command loop
+    count: Arc<AtomicUsize>,
+}
+
+impl OutputFiles {
+    pub fn new(dir: PathBuf, template: String) -> io::Result<OutputFiles> {
+        fs::create_dir_all(&dir)?;
+        Ok(OutputFiles { dir, template, host: String::new(), count: Arc::new(AtomicUsize::new(0)) })
+    }
+
+    // numbered from 1 again, with several hosts each gets a directory of its own
+    // unless the template tells them apart
+    pub fn for_host(&self, host: &str, own_dir: bool) -> OutputFiles {
+        let dir = match own_dir && !self.template.contains("{host}") {
+            true => self.dir.join(sanitize(host)),
+            false => self.dir.clone(),
+        };
+        OutputFiles { dir, template: self.template.clone(), host: host.to_string(), count: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    // raw responses are written as they are, text ones without formatting codes and with a final newline
+    pub fn write(&self, command: &str, output: &[u8], raw: bool) -> io::Result<PathBuf> {
+        let index = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        let name = self.template
+            .replace("{index}", &format!("{:03}", index))
+            .replace("{command}", &sanitize(command))
+            .replace("{host}", &sanitize(&self.host));
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(name);
+        if raw {
+            fs::write(&path, output)?;
+        } else {
+            let mut text = ColorMode::Strip.apply(&String::from_utf8_lossy(output));
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            fs::write(&path, text)?;
+        }
+        Ok(path)
+    }
+}
+
+// "whitelist list" becomes "whitelist_list", nothing that could leave the directory
+fn sanitize(text: &str) -> String {
+    let mut name = String::new();
+    for c in text.trim().chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' => name.push(c),
+            _ if !name.ends_with('_') => name.push('_'),
+            _ => {}
+        }
+        if name.len() >= MAX_COMMAND_LENGTH {
+            break;
+        }
+    }
+    let name = name.trim_matches(|c| c == '_' || c == '.').to_string();
+    match name.is_empty() {
+        true => "_".to_string(),
+        false => name,
+    }
+}
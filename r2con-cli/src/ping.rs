@@ -0,0 +1,38 @@
+use std::{process::ExitCode, time::Duration};
+
+use r2con::slp;
+
+use crate::{EXIT_CONNECTION, EXIT_USAGE};
+
+const DEFAULT_GAME_PORT: u16 = 25565;
+
+// the connect timeout covers the whole ping, it is a single short exchange
+pub async fn run_ping(host: &str, quiet: bool, timeout: Option<Duration>) -> ExitCode {
+    let (hostname, port) = match host.rsplit_once(':') {
+        Some((hostname, port)) => {
+            if let Ok(port) = port.parse::<u16>() {
+                (hostname, port)
+            } else {
+                eprintln!("error: invalid port '{}'", port);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+        None => (host, DEFAULT_GAME_PORT),
+    };
+
+    match slp::status_with_timeout(hostname, port, timeout).await {
+        Ok(status) => {
+            if !quiet {
+                println!("Version: {}", status.version);
+                println!("MOTD: {}", status.motd);
+                println!("Players: {}/{}", status.online_players, status.max_players);
+                println!("Latency: {}ms", status.latency.as_millis());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("ping Error: {}", e);
+            ExitCode::from(EXIT_CONNECTION)
+        }
+    }
+}
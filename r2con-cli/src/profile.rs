@@ -0,0 +1,226 @@
+use std::{collections::BTreeMap, error::Error, fs, path::{Path, PathBuf}, process::Command, time::Duration};
+use serde::{Deserialize, Serialize};
+
+use r2con::RconConfig;
+
+use crate::connection::{GameDialect, Protocol, Target};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    // program printing the password, run through the shell (e.g. `pass show mc/rcon`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
+    // the password is kept in the OS credential store under the profile's name
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub use_keyring: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<GameDialect>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_time: Option<f64>,
+    // interactive prompt for this server, see ConfigFile::prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    // defaults to ~/.local/share/r2con/history
+    pub path: Option<PathBuf>,
+    // commands starting with one of these are not saved, e.g. "op" or "whitelist add"
+    pub exclude: Vec<String>,
+}
+
+impl Default for HistorySettings {
+    fn default() -> HistorySettings {
+        HistorySettings {
+            enabled: true,
+            path: None,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl HistorySettings {
+    pub fn file(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.path.clone().or_else(|| dirs::data_dir().map(|dir| dir.join("r2con").join("history")))
+    }
+
+    pub fn is_excluded(&self, command: &str) -> bool {
+        let command = command.to_lowercase();
+        self.exclude.iter().any(|prefix| command.starts_with(&prefix.to_lowercase()))
+    }
+
+    fn is_default(&self) -> bool {
+        *self == HistorySettings::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConfigFile {
+    // profile used when neither --profile nor --host is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default, skip_serializing_if = "HistorySettings::is_default")]
+    pub history: HistorySettings,
+    // interactive prompt with {host}, {port}, {profile} and {latency} placeholders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    // name = "command with {0} {1} placeholders", expanded in scripts and interactive mode
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("r2con").join("config.toml"))
+    }
+
+    pub fn load() -> Result<ConfigFile, Box<dyn Error + Send + Sync>> {
+        let path = match ConfigFile::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(ConfigFile::default()),
+        };
+        let contents = fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|e| format!("invalid config file {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+        let path = ConfigFile::path().ok_or("could not find the config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_private(&path, &toml::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, Box<dyn Error + Send + Sync>> {
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile),
+            None => Err(format!("no profile named '{}'", name).into()),
+        }
+    }
+
+    // the given profiles, or all of them when none are given
+    pub fn profile_names(&self, names: Vec<String>) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let names: Vec<String> = match names.is_empty() {
+            true => self.profiles.keys().cloned().collect(),
+            false => names,
+        };
+        if names.is_empty() {
+            return Err("no profiles, add one with `r2con config add`".into());
+        }
+        Ok(names)
+    }
+
+    // connection settings of an rcon profile for the long running subcommands,
+    // the password is asked for up front when the profile doesn't provide one
+    pub async fn target(&self, name: &str, connect_timeout: Option<Duration>) -> Result<(Target, GameDialect), Box<dyn Error + Send + Sync>> {
+        let profile = self.profile(name)?;
+        let host = profile.host.clone().ok_or("the profile has no host")?;
+        let port = profile.port.unwrap_or(Protocol::Rcon.default_port());
+        let password = match profile.get_password(name)? {
+            Some(password) => password,
+            None => {
+                let prompt = format!("Password for {}: ", name);
+                tokio::task::spawn_blocking(move || rpassword::prompt_password(prompt)).await??
+            }
+        };
+        let dialect = profile.dialect.unwrap_or(GameDialect::Minecraft);
+        let target = Target {
+            protocol: Protocol::Rcon,
+            rcon_config: RconConfig::new().dialect(dialect.into()),
+            addr: format!("{}:{}", host, port),
+            password,
+            connect_timeout,
+        };
+        Ok((target, dialect))
+    }
+}
+
+impl Profile {
+    pub fn get_password(&self, name: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        if let Some(password) = &self.password {
+            return Ok(Some(password.clone()));
+        }
+        if self.use_keyring {
+            return keyring_password(name).map(Some);
+        }
+        match &self.password_command {
+            Some(command) => run_password_command(command).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+const KEYRING_SERVICE: &str = "r2con";
+
+#[cfg(feature = "keyring")]
+fn keyring_password(name: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Ok(keyring::Entry::new(KEYRING_SERVICE, name)?.get_password()?)
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_password(_name: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    Err("the profile's password is in the keyring but r2con was built without the keyring feature".into())
+}
+
+#[cfg(feature = "keyring")]
+pub fn store_keyring_password(name: &str, password: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(password)?;
+    Ok(())
+}
+
+#[cfg(feature = "keyring")]
+pub fn delete_keyring_password(name: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match keyring::Entry::new(KEYRING_SERVICE, name)?.delete_credential() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn run_password_command(command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()?
+    } else {
+        Command::new("sh").args(["-c", command]).output()?
+    };
+    if !output.status.success() {
+        return Err(format!("password command failed with {}", output.status).into());
+    }
+    let stdout = String::from_utf8(output.stdout)?;
+    Ok(stdout.lines().next().unwrap_or("").to_string())
+}
+
+// the file can hold passwords, keep it readable by the owner only
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use std::{io::Write, os::unix::fs::{OpenOptionsExt, PermissionsExt}};
+    let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    // mode only applies to new files
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::process::ExitCode;
+
+use r2con::A2sClient;
+
+use crate::EXIT_CONNECTION;
+
+const DEFAULT_QUERY_PORT: u16 = 27015;
+
+pub async fn run_query(host: &str, quiet: bool) -> ExitCode {
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, DEFAULT_QUERY_PORT)
+    };
+
+    let client = match A2sClient::connect(addr).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("connection Error: {}", e);
+            return ExitCode::from(EXIT_CONNECTION);
+        }
+    };
+
+    match client.info().await {
+        Ok(info) => {
+            if !quiet {
+                println!("Name: {}", info.name);
+                println!("Map: {}", info.map);
+                println!("Players: {}/{}", info.players, info.max_players);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("query Error: {}", e);
+            ExitCode::from(EXIT_CONNECTION)
+        }
+    }
+}
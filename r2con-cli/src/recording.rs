@@ -0,0 +1,85 @@
+use std::{error::Error, fs::{self, File}, io::Write, path::Path, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use serde::{Deserialize, Serialize};
+
+// one command of a recorded session, stored as a line of JSON
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub host: String,
+    // when the command was sent, counted from the start of the recording
+    pub offset_ms: u128,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u128,
+}
+
+// writes every command with its timing and response, shared by the connections of one run
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+    start: Instant,
+    host: String,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Recorder, Box<dyn Error + Send + Sync>> {
+        let file = File::create(path)?;
+        Ok(Recorder { file: Arc::new(Mutex::new(file)), start: Instant::now(), host: String::new() })
+    }
+
+    pub fn for_host(&self, host: &str) -> Recorder {
+        Recorder { host: host.to_string(), ..self.clone() }
+    }
+
+    pub fn record(&self, command: &str, sent: Instant, result: &Result<String, Box<dyn Error + Send + Sync>>) {
+        let entry = Entry {
+            host: self.host.clone(),
+            offset_ms: sent.duration_since(self.start).as_millis(),
+            command: command.to_string(),
+            response: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            duration_ms: sent.elapsed().as_millis(),
+        };
+        // like the session log, a failed write shouldn't end the session
+        if let (Ok(line), Ok(mut file)) = (serde_json::to_string(&entry), self.file.lock()) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    pub fn flush(&self) {
+        if let Ok(file) = self.file.lock() {
+            let _ = file.sync_data();
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>, Box<dyn Error + Send + Sync>> {
+    let contents = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let entry = serde_json::from_str(line).map_err(|e| format!("{} line {}: {}", path.display(), i+1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// the commands sent to the first recorded host, each with the original gap before it when keep_timing is set
+pub fn replay_commands(entries: &[Entry], keep_timing: bool) -> Vec<(Option<Duration>, String)> {
+    let host = match entries.first() {
+        Some(entry) => &entry.host,
+        None => return Vec::new(),
+    };
+    let mut commands = Vec::new();
+    let mut previous: Option<u128> = None;
+    for entry in entries.iter().filter(|entry| &entry.host == host) {
+        let gap = match (keep_timing, previous) {
+            (true, Some(previous)) => Some(Duration::from_millis(entry.offset_ms.saturating_sub(previous) as u64)),
+            _ => None,
+        };
+        previous = Some(entry.offset_ms);
+        commands.push((gap, entry.command.clone()));
+    }
+    commands
+}
@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, error::Error, fs, io::Write, time::{Duration, Instant}};
+use r2con::RconAuthError;
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
+
+use crate::{command_completion::{join_lines, CommandSet, ReplHelper}, connection::{is_disconnect, Connection, GameDialect}, expand_command, output::OutputOptions, profile::HistorySettings, run_command};
+
+pub const DEFAULT_PROMPT: &str = "{host}:{port}> ";
+
+const META_HELP: &str = "\
+:help             show this help
+:reconnect        log in again, e.g. after the server restarted
+:clear            clear the screen
+:history [n]      show the last n commands (all by default)
+:timeout [secs]   show or set the command timeout, 0 waits forever
+:host             show the server this session is connected to
+quit              close the session";
+
+pub struct ReplOptions {
+    pub output: OutputOptions,
+    pub history: HistorySettings,
+    // used to pick the command listing tab completion is built from, None for protocols without one
+    pub dialect: Option<GameDialect>,
+    // template with {host}, {port}, {profile} and {latency} placeholders
+    pub prompt: String,
+    pub profile: Option<String>,
+    pub aliases: BTreeMap<String, String>,
+    // --var values for {{key}} placeholders
+    pub variables: BTreeMap<String, String>,
+    // log in again when the server drops the connection, waiting reconnect_delay between tries
+    pub auto_reconnect: bool,
+    pub reconnect_delay: Duration,
+    pub rerun_on_reconnect: bool,
+}
+
+// emacs style editing: arrow keys for history, Ctrl-A/E, Ctrl-W and Ctrl-L
+pub async fn run(rcon_client: &mut Connection, options: &ReplOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper { commands: load_commands(rcon_client, options.dialect).await }));
+    let history_file = options.history.file();
+    if let Some(path) = &history_file {
+        // a missing file just means there is no history yet
+        let _ = editor.load_history(path);
+    }
+    let result = read_loop(&mut editor, rcon_client, options).await;
+    if let Some(path) = &history_file {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("warning: could not save the history to {}: {}", path.display(), e);
+        }
+    }
+    result
+}
+
+// completion is a convenience, servers that reject the listing command just don't get any
+async fn load_commands(rcon_client: &mut Connection, dialect: Option<GameDialect>) -> CommandSet {
+    let dialect = match dialect {
+        Some(dialect) => dialect,
+        None => return CommandSet::default(),
+    };
+    let listing = match dialect.help_command() {
+        Some(command) => command,
+        None => return CommandSet::default(),
+    };
+    match rcon_client.send_unrecorded(listing).await {
+        Ok(output) => CommandSet::parse(dialect, &output),
+        Err(_) => CommandSet::default(),
+    }
+}
+
+async fn read_loop(editor: &mut Editor<ReplHelper, DefaultHistory>, rcon_client: &mut Connection, options: &ReplOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // round trip of the last command, shown by {latency}
+    let mut latency = None;
+    // a second Ctrl-C in a row at the prompt ends the session
+    let mut interrupted = false;
+    loop {
+        // the editor blocks on the terminal, let the runtime move other tasks off this thread
+        let prompt = render_prompt(&options.prompt, rcon_client, options.profile.as_deref(), latency);
+        let line = tokio::task::block_in_place(|| editor.readline(&prompt));
+        if !matches!(line, Err(ReadlineError::Interrupted)) {
+            interrupted = false;
+        }
+        match line {
+            Ok(line) => {
+                let line = join_lines(&line);
+                let trimmed_line = line.trim();
+                if trimmed_line == "quit" {
+                    break;
+                } else if !trimmed_line.is_empty() {
+                    // a leading space keeps a command out of the history like in most shells
+                    if !line.starts_with(' ') && !options.history.is_excluded(trimmed_line) {
+                        editor.add_history_entry(trimmed_line)?;
+                    }
+                    if let Some(meta_command) = trimmed_line.strip_prefix(':') {
+                        run_meta_command(editor, rcon_client, meta_command).await;
+                    } else {
+                        let command = match expand_command(&options.aliases, &options.variables, trimmed_line) {
+                            Ok(command) => command,
+                            Err(e) => {
+                                eprintln!("error: {}", e);
+                                continue;
+                            }
+                        };
+                        // the error is already printed, :reconnect can recover a dropped connection
+                        let start = Instant::now();
+                        let result = tokio::select! {
+                            result = run_command(rcon_client, &command, &options.output) => Some(result),
+                            _ = tokio::signal::ctrl_c() => None,
+                        };
+                        match result {
+                            Some(Err(e)) if options.auto_reconnect && is_disconnect(e.as_ref()) => auto_reconnect(rcon_client, options, &command).await,
+                            Some(_) => latency = Some(start.elapsed()),
+                            None => cancel_command(rcon_client).await,
+                        }
+                    }
+                }
+            }
+            // Ctrl-C discards the current line like in a shell
+            Err(ReadlineError::Interrupted) if interrupted => break,
+            Err(ReadlineError::Interrupted) => {
+                interrupted = true;
+                eprintln!("(press Ctrl-C again, Ctrl-D or type quit to exit)");
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+// the response of an abandoned command could still arrive and be taken for the next one's,
+// a fresh connection can't mix them up
+async fn cancel_command(rcon_client: &mut Connection) {
+    eprintln!("Cancelled");
+    if let Err(e) = rcon_client.reconnect().await {
+        eprintln!("error: could not reconnect: {}, use :reconnect to try again", e);
+    }
+}
+
+// keeps trying with a countdown until the server is back, Ctrl-C gives up
+async fn auto_reconnect(rcon_client: &mut Connection, options: &ReplOptions, failed_command: &str) {
+    let addr = rcon_client.target().addr.clone();
+    let wait = options.reconnect_delay.as_secs().max(1);
+    let reconnect = async {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            for remaining in (1..=wait).rev() {
+                eprint!("\rConnection lost, reconnecting in {}s (attempt {}, Ctrl-C to stop)   ", remaining, attempt);
+                let _ = std::io::stderr().flush();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            match rcon_client.reconnect().await {
+                Ok(_) => return Ok(()),
+                // a changed password won't come back by waiting
+                Err(e) if e.is::<RconAuthError>() => return Err(e),
+                Err(_) => continue,
+            }
+        }
+    };
+    let result = tokio::select! {
+        result = reconnect => Some(result),
+        _ = tokio::signal::ctrl_c() => None,
+    };
+    eprintln!();
+    match result {
+        Some(Ok(_)) => eprintln!("Reconnected to {}", addr),
+        Some(Err(e)) => {
+            eprintln!("error: could not reconnect: {}", e);
+            return;
+        }
+        None => {
+            eprintln!("Stopped reconnecting, use :reconnect to try again");
+            return;
+        }
+    }
+    if options.rerun_on_reconnect {
+        let _ = run_command(rcon_client, failed_command, &options.output).await;
+    }
+}
+
+// commands starting with ':' are handled here and never sent to the server
+async fn run_meta_command(editor: &mut Editor<ReplHelper, DefaultHistory>, rcon_client: &mut Connection, line: &str) {
+    let mut args = line.split_whitespace();
+    match (args.next().unwrap_or(""), args.next()) {
+        ("help", _) => println!("{}", META_HELP),
+        ("reconnect", _) => match rcon_client.reconnect().await {
+            Ok(_) => println!("Reconnected to {}", rcon_client.target().addr),
+            Err(e) => eprintln!("error: could not reconnect: {}", e),
+        },
+        ("clear", _) => {
+            if let Err(e) = editor.clear_screen() {
+                eprintln!("error: could not clear the screen: {}", e);
+            }
+        }
+        ("history", count) => {
+            let entries: Vec<&String> = editor.history().iter().collect();
+            let count = match count.map(|count| count.parse::<usize>()) {
+                Some(Ok(count)) => count,
+                Some(Err(_)) => {
+                    eprintln!("error: :history takes a number of entries");
+                    return;
+                }
+                None => entries.len(),
+            };
+            let start = entries.len().saturating_sub(count);
+            for (i, entry) in entries.iter().enumerate().skip(start) {
+                println!("{:>5}  {}", i+1, entry);
+            }
+        }
+        ("timeout", None) => match rcon_client.command_timeout() {
+            Some(deadline) => println!("Commands time out after {}s", deadline.as_secs_f64()),
+            None => println!("Commands never time out"),
+        },
+        ("timeout", Some(seconds)) => match seconds.parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 => rcon_client.set_command_timeout(Some(Duration::from_secs_f64(seconds)).filter(|deadline| !deadline.is_zero())),
+            _ => eprintln!("error: :timeout takes a number of seconds"),
+        },
+        ("host", _) => {
+            let target = rcon_client.target();
+            println!("{} ({})", target.addr, format!("{:?}", target.protocol).to_lowercase());
+        }
+        (name, _) => eprintln!("error: unknown command ':{}', see :help", name),
+    }
+}
+
+fn render_prompt(template: &str, rcon_client: &Connection, profile: Option<&str>, latency: Option<Duration>) -> String {
+    let addr = &rcon_client.target().addr;
+    let (host, port) = addr.rsplit_once(':').unwrap_or((addr, ""));
+    let latency = match latency {
+        Some(latency) => format!("{}ms", latency.as_millis()),
+        None => "-".to_string(),
+    };
+    template.replace("{host}", host)
+        .replace("{port}", port)
+        .replace("{profile}", profile.unwrap_or(""))
+        .replace("{latency}", &latency)
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, env, error::Error, net::SocketAddr, process::ExitCode, sync::Arc, time::Duration};
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+use r2con::RconAuthError;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{connection::{is_timeout, Connection, Target}, profile::ConfigFile, shutdown::Shutdown, EXIT_CONNECTION, EXIT_USAGE};
+
+// well below the shortest idle limit of the supported games
+const KEEPALIVE_CHECK: Duration = Duration::from_secs(10);
+
+// a profile's connection is opened on its first request and kept for the next ones,
+// the lock keeps one command in flight per server like the rest of the cli
+struct Server {
+    target: Target,
+    connection: Mutex<Option<Connection>>,
+}
+
+struct ServeState {
+    token: String,
+    command_timeout: Option<Duration>,
+    servers: BTreeMap<String, Server>,
+}
+
+#[derive(Deserialize)]
+struct CommandRequest {
+    command: String,
+}
+
+#[derive(Serialize)]
+struct CommandReply {
+    profile: String,
+    command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn run_serve(listen: SocketAddr, token: Option<String>, profiles: Vec<String>, connect_timeout: Option<Duration>, command_timeout: Option<Duration>) -> ExitCode {
+    let token = match token.or_else(|| env::var("R2CON_SERVE_TOKEN").ok()) {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            eprintln!("error: a token is required, pass --token or set R2CON_SERVE_TOKEN");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let config = match ConfigFile::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let names = match config.profile_names(profiles) {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let mut servers = BTreeMap::new();
+    for name in names {
+        match config.target(&name, connect_timeout).await {
+            Ok((target, _)) => {
+                servers.insert(name, Server { target, connection: Mutex::new(None) });
+            }
+            Err(e) => {
+                eprintln!("error: profile '{}': {}", name, e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let state = Arc::new(ServeState { token, command_timeout, servers });
+    tokio::spawn(keepalive_loop(state.clone()));
+    let app = Router::new().route("/servers/:profile/command", post(command)).with_state(state.clone());
+    let listener = match tokio::net::TcpListener::bind(listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: could not listen on {}: {}", listen, e);
+            return ExitCode::from(EXIT_CONNECTION);
+        }
+    };
+    eprintln!("serving on http://{}", listen);
+    // requests in flight are answered before the connections are closed
+    let shutdown = Shutdown::listen();
+    let stop = shutdown.clone();
+    let result = axum::serve(listener, app).with_graceful_shutdown(async move {
+        tokio::select! {
+            terminated = stop.terminated() => eprintln!("received {}, closing the connections", terminated.signal),
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }).await;
+    for server in state.servers.values() {
+        if let Some(connection) = server.connection.lock().await.take() {
+            let _ = connection.close().await;
+        }
+    }
+    match result {
+        Ok(_) => match shutdown.received() {
+            Some(terminated) => ExitCode::from(terminated.exit_code()),
+            None => ExitCode::SUCCESS,
+        },
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_CONNECTION)
+        }
+    }
+}
+
+async fn command(State(state): State<Arc<ServeState>>, Path(profile): Path<String>, headers: HeaderMap, Json(request): Json<CommandRequest>) -> (StatusCode, Json<CommandReply>) {
+    let reply = |status: StatusCode, result: Result<String, String>| {
+        let (response, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        (status, Json(CommandReply { profile: profile.clone(), command: request.command.clone(), response, error }))
+    };
+    if !authorized(&headers, &state.token) {
+        return reply(StatusCode::UNAUTHORIZED, Err("missing or wrong bearer token".to_string()));
+    }
+    let server = match state.servers.get(&profile) {
+        Some(server) => server,
+        None => return reply(StatusCode::NOT_FOUND, Err(format!("no profile named '{}'", profile))),
+    };
+    match send(server, &request.command, state.command_timeout).await {
+        Ok(response) => reply(StatusCode::OK, Ok(response)),
+        Err(e) if e.is::<RconAuthError>() => reply(StatusCode::BAD_GATEWAY, Err(format!("wrong password: {}", e))),
+        Err(e) if is_timeout(e.as_ref()) => reply(StatusCode::GATEWAY_TIMEOUT, Err(e.to_string())),
+        Err(e) => reply(StatusCode::BAD_GATEWAY, Err(e.to_string())),
+    }
+}
+
+// keeps the cached connections of idle profiles open, a broken one is dropped like in send
+async fn keepalive_loop(state: Arc<ServeState>) {
+    loop {
+        tokio::time::sleep(KEEPALIVE_CHECK).await;
+        for server in state.servers.values() {
+            let mut slot = server.connection.lock().await;
+            let failed = match slot.as_mut() {
+                Some(connection) => connection.keepalive().await.is_err(),
+                None => false,
+            };
+            if failed {
+                if let Some(connection) = slot.take() {
+                    let _ = connection.close().await;
+                }
+            }
+        }
+    }
+}
+
+// a broken connection is dropped so the next request opens a new one
+async fn send(server: &Server, command: &str, command_timeout: Option<Duration>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut slot = server.connection.lock().await;
+    if slot.is_none() {
+        let client = server.target.connect().await?;
+        *slot = Some(Connection::new(server.target.clone(), client, command_timeout, None, None));
+    }
+    let result = slot.as_mut().unwrap().send_command(command).await;
+    if result.is_err() {
+        if let Some(connection) = slot.take() {
+            let _ = connection.close().await;
+        }
+    }
+    result
+}
+
+fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    let given = match headers.get("authorization").and_then(|value| value.to_str().ok()).and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(given) => given,
+        None => return false,
+    };
+    // compares every byte so the time taken doesn't tell how much of the token was right
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -0,0 +1,55 @@
+use std::{fs::{File, OpenOptions}, io::{self, Write}, path::Path, sync::{Arc, Mutex}, time::SystemTime};
+
+// appends a timestamped transcript of every command and response, whatever is printed
+#[derive(Clone, Debug)]
+pub struct SessionLog {
+    file: Arc<Mutex<File>>,
+    // the server the lines belong to, several can share one file
+    label: String,
+}
+
+impl SessionLog {
+    pub fn open(path: &Path) -> io::Result<SessionLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SessionLog { file: Arc::new(Mutex::new(file)), label: String::new() })
+    }
+
+    pub fn for_host(&self, label: &str) -> SessionLog {
+        SessionLog { file: self.file.clone(), label: label.to_string() }
+    }
+
+    pub fn command(&self, command: &str) {
+        self.write('>', command);
+    }
+
+    pub fn response(&self, output: &str) {
+        self.write('<', output);
+    }
+
+    pub fn error(&self, error: &str) {
+        self.write('!', error);
+    }
+
+    // makes sure the transcript is on disk before the process exits
+    pub fn flush(&self) {
+        if let Ok(file) = self.file.lock() {
+            let _ = file.sync_data();
+        }
+    }
+
+    // one line per response line so the transcript stays greppable
+    fn write(&self, marker: char, text: &str) {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let mut entry = String::new();
+        for line in text.lines() {
+            entry.push_str(&format!("{} {} {} {}\n", timestamp, self.label, marker, line));
+        }
+        if entry.is_empty() {
+            entry = format!("{} {} {}\n", timestamp, self.label, marker);
+        }
+        // the log is best effort, a full disk shouldn't stop the session
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(entry.as_bytes());
+        }
+    }
+}
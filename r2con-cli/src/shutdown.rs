@@ -0,0 +1,85 @@
+use std::{error::Error, fmt, process, time::Duration};
+use tokio::sync::watch;
+
+// how long the sessions get to close their connections before the process exits anyway,
+// e.g. when a prompt is blocked waiting for a line
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// returned by a session that stopped because of SIGTERM or SIGHUP
+#[derive(Debug, Clone, Copy)]
+pub struct Terminated {
+    pub signal: &'static str,
+    signal_number: u8,
+}
+
+impl Terminated {
+    // the shell convention for a process ended by a signal
+    pub fn exit_code(&self) -> u8 {
+        128 + self.signal_number
+    }
+}
+
+impl fmt::Display for Terminated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "received {}, closing the connection", self.signal)
+    }
+}
+
+impl Error for Terminated {}
+
+// tells every session when the process is asked to stop
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<Option<Terminated>>,
+}
+
+impl Shutdown {
+    // installs the handlers, from here on SIGTERM and SIGHUP no longer end the process right away
+    pub fn listen() -> Shutdown {
+        let (sender, receiver) = watch::channel(None);
+        tokio::spawn(async move {
+            let terminated = wait_for_signal().await;
+            let _ = sender.send(Some(terminated));
+            tokio::time::sleep(GRACE_PERIOD).await;
+            process::exit(terminated.exit_code() as i32);
+        });
+        Shutdown { receiver }
+    }
+
+    // the signal received so far, without waiting for one
+    pub fn received(&self) -> Option<Terminated> {
+        *self.receiver.borrow()
+    }
+
+    pub async fn terminated(&self) -> Terminated {
+        let mut receiver = self.receiver.clone();
+        loop {
+            if let Some(terminated) = *receiver.borrow_and_update() {
+                return terminated;
+            }
+            if receiver.changed().await.is_err() {
+                // the listener is gone without a signal, nothing will come anymore
+                return std::future::pending().await;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> Terminated {
+    use tokio::signal::unix::{signal, SignalKind};
+    let (mut terminate, mut hangup) = match (signal(SignalKind::terminate()), signal(SignalKind::hangup())) {
+        (Ok(terminate), Ok(hangup)) => (terminate, hangup),
+        _ => return std::future::pending().await,
+    };
+    tokio::select! {
+        _ = terminate.recv() => Terminated { signal: "SIGTERM", signal_number: 15 },
+        _ = hangup.recv() => Terminated { signal: "SIGHUP", signal_number: 1 },
+    }
+}
+
+// windows has no equivalent the CLI could be stopped with
+#[cfg(not(unix))]
+async fn wait_for_signal() -> Terminated {
+    std::future::pending().await
+}
@@ -0,0 +1,107 @@
+use clap::ValueEnum;
+use r2con::{ark, minecraft, source};
+
+use crate::connection::GameDialect;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum OutputFormat {
+    /// Responses as the server sent them
+    #[default]
+    Text,
+    /// Player and ban lists as aligned columns
+    Table,
+    /// Player and ban lists as CSV with a header row
+    Csv,
+}
+
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Csv => self.render_csv(),
+            _ => self.render_columns(),
+        }
+    }
+
+    fn render_columns(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|header| header.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let format_row = |cells: Vec<&str>| -> String {
+            let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        let mut lines = vec![format_row(self.headers.clone())];
+        for row in &self.rows {
+            lines.push(format_row(row.iter().map(|cell| cell.as_str()).collect()));
+        }
+        lines.join("\n")
+    }
+
+    fn render_csv(&self) -> String {
+        let mut lines = vec![self.headers.iter().map(|header| csv_field(header)).collect::<Vec<String>>().join(",")];
+        for row in &self.rows {
+            lines.push(row.iter().map(|cell| csv_field(cell)).collect::<Vec<String>>().join(","));
+        }
+        lines.join("\n")
+    }
+}
+
+// quoted only when needed, quotes inside are doubled
+fn csv_field(value: &str) -> String {
+    match value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+// picks the parser from the command, None when it isn't a list r2con knows or the output didn't parse
+pub fn parse(dialect: GameDialect, command: &str, output: &str) -> Option<Table> {
+    let words: Vec<String> = command.split_whitespace().map(|word| word.trim_start_matches('/').to_lowercase()).collect();
+    let words: Vec<&str> = words.iter().map(|word| word.as_str()).collect();
+    match (dialect, words.as_slice()) {
+        (GameDialect::Minecraft, ["list", ..]) => {
+            let list = minecraft::parse_list(output)?;
+            let with_uuids = list.players.iter().any(|player| player.uuid.is_some());
+            Some(Table {
+                headers: match with_uuids {
+                    true => vec!["NAME", "UUID"],
+                    false => vec!["NAME"],
+                },
+                rows: list.players.into_iter().map(|player| match with_uuids {
+                    true => vec![player.name, player.uuid.unwrap_or_default()],
+                    false => vec![player.name],
+                }).collect(),
+            })
+        }
+        (GameDialect::Minecraft, ["whitelist", "list"]) => Some(Table {
+            headers: vec!["NAME"],
+            rows: minecraft::parse_whitelist(output)?.into_iter().map(|name| vec![name]).collect(),
+        }),
+        (GameDialect::Minecraft, ["banlist", ..]) => Some(Table {
+            headers: vec!["TARGET", "SOURCE", "REASON"],
+            rows: minecraft::parse_banlist(output)?.into_iter().map(|ban| vec![ban.target, ban.source, ban.reason]).collect(),
+        }),
+        (GameDialect::Source, ["status"]) => Some(Table {
+            headers: vec!["USERID", "NAME", "STEAMID", "PING"],
+            rows: source::parse_status(output)?.players.into_iter().map(|player| vec![
+                player.userid.to_string(),
+                player.name,
+                player.steamid,
+                player.ping.map(|ping| ping.to_string()).unwrap_or_default(),
+            ]).collect(),
+        }),
+        (GameDialect::Ark, ["listplayers"]) => Some(Table {
+            headers: vec!["INDEX", "NAME", "ID"],
+            rows: ark::parse_list_players(output)?.into_iter().map(|player| vec![player.index.to_string(), player.name, player.id]).collect(),
+        }),
+        _ => None,
+    }
+}
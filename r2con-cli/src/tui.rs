@@ -0,0 +1,340 @@
+use std::{error::Error, io::{self, Stdout}, process::ExitCode, time::{Duration, Instant}};
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{event::{self, Event, KeyCode, KeyEventKind, KeyModifiers}, execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}},
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Text},
+    widgets::{Block, Paragraph, Tabs},
+    Frame, Terminal,
+};
+
+use r2con::ColorMode;
+use tokio::{sync::mpsc::{self, UnboundedReceiver, UnboundedSender}, time::MissedTickBehavior};
+
+use crate::{connection::{Connection, GameDialect, Target}, profile::ConfigFile, EXIT_USAGE};
+
+// how often the sidebar asks every server for its players
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// the UI redraws at least this often while waiting for keys
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// older lines are dropped so long sessions don't grow without bound
+const MAX_OUTPUT_LINES: usize = 5000;
+const SIDEBAR_WIDTH: u16 = 32;
+// how long quitting waits for the workers to close their connections
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct ServerTab {
+    name: String,
+    target: Target,
+    // the tab's worker owns the connection, the UI only sends it requests
+    requests: UnboundedSender<Request>,
+    status: String,
+    latency: Option<Duration>,
+    players: Option<String>,
+    output: Vec<String>,
+    // lines scrolled up from the bottom of the output
+    scroll: usize,
+}
+
+impl ServerTab {
+    fn push_output(&mut self, text: &str) {
+        self.output.extend(text.lines().map(|line| ColorMode::Strip.apply(line)));
+        if self.output.len() > MAX_OUTPUT_LINES {
+            let excess = self.output.len()-MAX_OUTPUT_LINES;
+            self.output.drain(..excess);
+        }
+        self.scroll = 0;
+    }
+
+    fn request(&mut self, request: Request) {
+        if self.requests.send(request).is_err() {
+            self.push_output("error: the connection task stopped");
+        }
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Status(status) => self.status = status,
+            Update::Latency(latency) => self.latency = Some(latency),
+            Update::Players(players) => self.players = players,
+            Update::Output(output) => self.push_output(&output),
+        }
+    }
+}
+
+enum Request {
+    Command(String),
+    Reconnect,
+}
+
+enum Update {
+    Status(String),
+    Latency(Duration),
+    Players(Option<String>),
+    Output(String),
+}
+
+// runs a tab's connects, commands and refreshes off the UI loop so a server that
+// doesn't answer only stalls its own tab
+struct Worker {
+    index: usize,
+    dialect: GameDialect,
+    target: Target,
+    command_timeout: Option<Duration>,
+    connection: Option<Connection>,
+    updates: UnboundedSender<(usize, Update)>,
+}
+
+impl Worker {
+    fn update(&self, update: Update) {
+        let _ = self.updates.send((self.index, update));
+    }
+
+    async fn run(mut self, mut requests: UnboundedReceiver<Request>) {
+        self.connect().await;
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+        refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(Request::Command(command)) => self.send(&command).await,
+                    Some(Request::Reconnect) => self.connect().await,
+                    None => break,
+                },
+                _ = refresh.tick() => self.refresh().await,
+            }
+        }
+        if let Some(connection) = self.connection.take() {
+            let _ = connection.close().await;
+        }
+    }
+
+    async fn connect(&mut self) {
+        self.update(Update::Status("connecting".to_string()));
+        if let Some(connection) = self.connection.take() {
+            let _ = connection.close().await;
+        }
+        match self.target.connect().await {
+            Ok(client) => {
+                self.connection = Some(Connection::new(self.target.clone(), client, self.command_timeout, None, None));
+                self.update(Update::Status("connected".to_string()));
+            }
+            Err(e) => self.update(Update::Status(format!("disconnected: {}", e))),
+        }
+    }
+
+    async fn send(&mut self, command: &str) {
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => {
+                self.update(Update::Output("not connected, press Ctrl-R to reconnect".to_string()));
+                return;
+            }
+        };
+        let start = Instant::now();
+        match connection.send_command(command).await {
+            Ok(output) => {
+                self.update(Update::Latency(start.elapsed()));
+                self.update(Update::Output(output));
+            }
+            Err(e) => {
+                self.update(Update::Status(format!("error: {}", e)));
+                self.update(Update::Output(format!("error: {}", e)));
+            }
+        }
+    }
+
+    // the player command doubles as the latency probe
+    async fn refresh(&mut self) {
+        let command = self.dialect.players_command();
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => return,
+        };
+        let start = Instant::now();
+        match connection.send_command(command).await {
+            Ok(output) => {
+                let players = self.dialect.player_count(&output).map(|(online, max)| match max {
+                    Some(max) => format!("{}/{}", online, max),
+                    None => online.to_string(),
+                });
+                self.update(Update::Latency(start.elapsed()));
+                self.update(Update::Players(players));
+                self.update(Update::Status("connected".to_string()));
+            }
+            Err(e) => self.update(Update::Status(format!("error: {}", e))),
+        }
+    }
+}
+
+struct App {
+    tabs: Vec<ServerTab>,
+    selected: usize,
+    input: String,
+    updates: UnboundedReceiver<(usize, Update)>,
+}
+
+// tabs for the given profiles, or every profile in the config file
+pub async fn run_tui(profiles: Vec<String>, connect_timeout: Option<Duration>, command_timeout: Option<Duration>) -> ExitCode {
+    let config = match ConfigFile::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let names = match config.profile_names(profiles) {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let (updates_tx, updates) = mpsc::unbounded_channel();
+    let mut tabs = Vec::new();
+    let mut workers = Vec::new();
+    for (index, name) in names.into_iter().enumerate() {
+        let (target, dialect) = match config.target(&name, connect_timeout).await {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("error: profile '{}': {}", name, e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        };
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        workers.push((Worker { index, dialect, target: target.clone(), command_timeout, connection: None, updates: updates_tx.clone() }, requests_rx));
+        tabs.push(ServerTab {
+            name,
+            target,
+            requests,
+            status: "disconnected".to_string(),
+            latency: None,
+            players: None,
+            output: Vec::new(),
+            scroll: 0,
+        });
+    }
+    let handles: Vec<_> = workers.into_iter().map(|(worker, requests)| tokio::spawn(worker.run(requests))).collect();
+
+    let mut terminal = match enter_terminal() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("error: could not set up the terminal: {}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let mut app = App { tabs, selected: 0, input: String::new(), updates };
+    let result = event_loop(&mut terminal, &mut app).await;
+    let _ = leave_terminal(&mut terminal);
+    // dropping the tabs closes the request channels, workers close their connection and stop
+    drop(app);
+    for handle in handles {
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, handle).await;
+    }
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+fn enter_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn leave_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+// only draws and handles keys, the workers report back through app.updates
+async fn event_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> Result<(), Box<dyn Error + Send + Sync>> {
+    loop {
+        while let Ok((index, update)) = app.updates.try_recv() {
+            app.tabs[index].apply(update);
+        }
+        terminal.draw(|frame| draw(frame, app))?;
+
+        // crossterm blocks on the terminal, let the runtime move other tasks off this thread
+        let event = tokio::task::block_in_place(|| -> io::Result<Option<Event>> {
+            match event::poll(POLL_INTERVAL)? {
+                true => event::read().map(Some),
+                false => Ok(None),
+            }
+        })?;
+        let key = match event {
+            Some(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let tab = &mut app.tabs[app.selected];
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') | KeyCode::Char('d') if ctrl => return Ok(()),
+            KeyCode::Char('r') if ctrl => tab.request(Request::Reconnect),
+            KeyCode::Tab => app.selected = (app.selected+1) % app.tabs.len(),
+            KeyCode::BackTab => app.selected = (app.selected+app.tabs.len()-1) % app.tabs.len(),
+            KeyCode::PageUp => tab.scroll = (tab.scroll+10).min(tab.output.len()),
+            KeyCode::PageDown => tab.scroll = tab.scroll.saturating_sub(10),
+            KeyCode::Enter => {
+                let command = app.input.trim().to_string();
+                app.input.clear();
+                if !command.is_empty() {
+                    tab.push_output(&format!("> {}", command));
+                    tab.request(Request::Command(command));
+                }
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Char(c) if !ctrl => app.input.push(c),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [tabs_area, body_area, input_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(3), Constraint::Length(3)]).areas(frame.area());
+    let [output_area, sidebar_area] = Layout::horizontal([Constraint::Min(10), Constraint::Length(SIDEBAR_WIDTH)]).areas(body_area);
+    let tab = &app.tabs[app.selected];
+
+    let titles: Vec<String> = app.tabs.iter().map(|tab| tab.name.clone()).collect();
+    let tabs = Tabs::new(titles)
+        .select(app.selected)
+        .block(Block::bordered().title("r2con (Tab: next server, Ctrl-R: reconnect, Esc: quit)"));
+    frame.render_widget(tabs, tabs_area);
+
+    frame.render_widget(output_pane(tab, output_area), output_area);
+
+    let latency = tab.latency.map_or("-".to_string(), |latency| format!("{}ms", latency.as_millis()));
+    let sidebar = Text::from(vec![
+        Line::from(format!("Host: {}", tab.target.addr)),
+        Line::from(format!("Status: {}", tab.status)),
+        Line::from(format!("Latency: {}", latency)),
+        Line::from(format!("Players: {}", tab.players.as_deref().unwrap_or("-"))),
+    ]);
+    frame.render_widget(Paragraph::new(sidebar).block(Block::bordered().title("Server")), sidebar_area);
+
+    let input = Paragraph::new(format!("> {}", app.input)).block(Block::bordered().title("Command"));
+    frame.render_widget(input, input_area);
+    frame.set_cursor_position((input_area.x + 3 + app.input.chars().count() as u16, input_area.y + 1));
+}
+
+// the newest lines at the bottom, PageUp moves back through older ones
+fn output_pane(tab: &ServerTab, area: Rect) -> Paragraph<'_> {
+    let height = area.height.saturating_sub(2) as usize;
+    let end = tab.output.len().saturating_sub(tab.scroll);
+    let start = end.saturating_sub(height);
+    let lines: Vec<Line> = tab.output[start..end].iter().map(|line| Line::from(line.as_str())).collect();
+    let title = match tab.scroll {
+        0 => tab.name.clone(),
+        scroll => format!("{} (scrolled up {} lines, PageDown to go back)", tab.name, scroll),
+    };
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
@@ -0,0 +1,58 @@
+use std::{collections::BTreeMap, env, error, fmt};
+
+#[derive(Debug)]
+pub enum VarError {
+    Undefined(String),
+    UndefinedEnv(String),
+    Unterminated(String),
+}
+
+impl fmt::Display for VarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VarError::Undefined(name) => write!(f, "variable '{}' is not set, pass it with --var {}=...", name, name),
+            VarError::UndefinedEnv(name) => write!(f, "environment variable '{}' is not set", name),
+            VarError::Unterminated(text) => write!(f, "unterminated placeholder in '{}'", text),
+        }
+    }
+}
+
+impl error::Error for VarError {}
+
+// `--var key=value`
+pub fn parse_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got '{}'", arg)),
+    }
+}
+
+// replaces {{name}} with --var values and ${NAME} with environment variables
+pub fn expand(vars: &BTreeMap<String, String>, text: &str) -> Result<String, VarError> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let next = [rest.find("{{"), rest.find("${")].into_iter().flatten().min();
+        let start = match next {
+            Some(start) => start,
+            None => break,
+        };
+        expanded.push_str(&rest[..start]);
+        let (closing, is_env) = if rest[start..].starts_with("{{") { ("}}", false) } else { ("}", true) };
+        let name_start = start+2;
+        let name_end = match rest[name_start..].find(closing) {
+            Some(len) => name_start+len,
+            None => return Err(VarError::Unterminated(text.to_string())),
+        };
+        let name = rest[name_start..name_end].trim();
+        let value = if is_env {
+            env::var(name).map_err(|_| VarError::UndefinedEnv(name.to_string()))?
+        } else {
+            vars.get(name).cloned().ok_or_else(|| VarError::Undefined(name.to_string()))?
+        };
+        expanded.push_str(&value);
+        rest = &rest[name_end+closing.len()..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
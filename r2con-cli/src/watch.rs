@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, error::Error, io::Write, time::{Duration, Instant}};
+use tokio::{signal, time::sleep};
+
+use r2con::ColorMode;
+
+use crate::{connection::Connection, output::OutputOptions};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HIGHLIGHT: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+pub struct WatchOptions {
+    pub interval: Duration,
+    // clear the terminal before each run, like watch(1)
+    pub clear: bool,
+    // highlight lines that changed since the previous run
+    pub diff: bool,
+}
+
+// runs until Ctrl-C or a command fails, reusing the connection instead of logging in every time
+// pauses are the sleep lines of --file scripts, see command_loop
+pub async fn run(rcon_client: &mut Connection, commands: &[String], pauses: &BTreeMap<usize, f64>, options: &WatchOptions, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    tokio::select! {
+        result = watch_loop(rcon_client, commands, pauses, options, output) => result,
+        _ = signal::ctrl_c() => Ok(()),
+    }
+}
+
+async fn watch_loop(rcon_client: &mut Connection, commands: &[String], pauses: &BTreeMap<usize, f64>, options: &WatchOptions, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // escapes would end up inside JSON strings or redirected files
+    let styled = !output.json && output.color == ColorMode::Ansi;
+    let mut previous: Vec<Option<String>> = vec![None; commands.len()];
+    loop {
+        if options.clear && styled && !output.verbosity.is_quiet() {
+            print!("{}", CLEAR_SCREEN);
+            let _ = std::io::stdout().flush();
+        }
+        for (i, command) in commands.iter().enumerate() {
+            if let Some(seconds) = pauses.get(&i) {
+                sleep(Duration::from_secs_f64(*seconds)).await;
+            }
+            let start = Instant::now();
+            let response = match rcon_client.send_command(command).await {
+                Ok(response) => response,
+                Err(e) => {
+                    output.print_command_error(command, &e.to_string(), start.elapsed());
+                    return Err(e);
+                }
+            };
+            match &previous[i] {
+                Some(before) if options.diff && styled => output.print_response(command, &highlight_changes(before, &response), start.elapsed()),
+                _ => output.print_response(command, &response, start.elapsed()),
+            }
+            previous[i] = Some(response);
+        }
+        sleep(options.interval).await;
+    }
+}
+
+// compares line by line, which is what changes in player lists and status output look like
+fn highlight_changes(before: &str, after: &str) -> String {
+    let before: Vec<&str> = before.lines().collect();
+    let lines: Vec<String> = after.lines().enumerate().map(|(i, line)| {
+        if before.get(i) == Some(&line) {
+            line.to_string()
+        } else {
+            format!("{}{}{}", HIGHLIGHT, line, RESET)
+        }
+    }).collect();
+    lines.join("\n")
+}
@@ -0,0 +1,34 @@
+#ifndef R2CON_H
+#define R2CON_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Strings must be NUL terminated UTF-8. Clients and strings must come from this
+   library and must not be used after being freed. A client must not be used from
+   two threads at once. A panic inside the library never unwinds into the caller,
+   it makes the call fail like any other error. */
+typedef struct RconClient RconClient;
+
+/* Connects and logs in, addr is "host:port". Returns NULL on failure. */
+RconClient *rcon_connect(const char *addr, const char *password);
+
+/* Sends a command and returns the response, release it with rcon_string_free.
+   Returns NULL on failure. */
+char *rcon_send(RconClient *client, const char *command);
+
+void rcon_string_free(char *s);
+
+/* Closes the connection and frees the client. */
+void rcon_free(RconClient *client);
+
+/* Message of the last failure on the calling thread, NULL if none.
+   Valid until the next failing call on the same thread. */
+const char *rcon_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,193 @@
+// the safety contract of every function is spelled out in include/r2con.h
+#![allow(clippy::missing_safety_doc)]
+
+use std::{any::Any, cell::RefCell, ffi::{c_char, CStr, CString}, panic::{self, AssertUnwindSafe}, ptr};
+
+use r2con::{blocking::RconClient, RconConfig};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message:String) {
+    // interior nul bytes would truncate the message anyway
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn panic_message(payload:Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    return "unknown panic".to_string();
+}
+
+// unwinding into C is undefined behavior, a panic is reported like any other failure.
+// a client that panicked may be left half way through a command, the caller should free it
+fn catch<T, F: FnOnce() -> T>(on_panic:T, f:F) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            set_last_error(format!("panic: {}", panic_message(payload)));
+            on_panic
+        }
+    }
+}
+
+unsafe fn read_str<'a>(s:*const c_char, name:&str) -> Option<&'a str> {
+    if s.is_null() {
+        set_last_error(format!("{} is null", name));
+        return None;
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_last_error(format!("{} is not valid UTF-8", name));
+            None
+        }
+    }
+}
+
+// returns NULL on failure, see rcon_last_error
+#[no_mangle]
+pub unsafe extern "C" fn rcon_connect(addr:*const c_char, password:*const c_char) -> *mut RconClient {
+    return catch(ptr::null_mut(), || {
+        let (addr, password) = match (read_str(addr, "addr"), read_str(password, "password")) {
+            (Some(addr), Some(password)) => (addr, password),
+            _ => return ptr::null_mut()
+        };
+        match RconClient::connect_with_config(&RconConfig::new(), addr, password) {
+            Ok(client) => Box::into_raw(Box::new(client)),
+            Err(e) => {
+                set_last_error(e.to_string());
+                ptr::null_mut()
+            }
+        }
+    });
+}
+
+// the returned string must be released with rcon_string_free, NULL on failure
+#[no_mangle]
+pub unsafe extern "C" fn rcon_send(client:*mut RconClient, command:*const c_char) -> *mut c_char {
+    return catch(ptr::null_mut(), || {
+        let client = match client.as_mut() {
+            Some(client) => client,
+            None => {
+                set_last_error("client is null".to_string());
+                return ptr::null_mut();
+            }
+        };
+        let command = match read_str(command, "command") {
+            Some(command) => command,
+            None => return ptr::null_mut()
+        };
+        match client.send_command(command) {
+            Ok(response) => CString::new(response.replace('\0', "")).unwrap_or_default().into_raw(),
+            Err(e) => {
+                set_last_error(e.to_string());
+                ptr::null_mut()
+            }
+        }
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rcon_string_free(s:*mut c_char) {
+    catch((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rcon_free(client:*mut RconClient) {
+    catch((), || {
+        if !client.is_null() {
+            let client = Box::from_raw(client);
+            let _ = client.close();
+        }
+    });
+}
+
+// valid until the next failing call on the same thread, NULL if nothing failed yet
+#[no_mangle]
+pub extern "C" fn rcon_last_error() -> *const c_char {
+    return catch(ptr::null(), || LAST_ERROR.with(|last| match last.borrow().as_ref() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null()
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use r2con::testing::MockServer;
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    fn last_error() -> String {
+        let message = rcon_last_error();
+        assert!(!message.is_null());
+        return unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string();
+    }
+
+    // the mock server runs on its own runtime, the blocking client brings another one
+    fn start_server(runtime:&Runtime) -> MockServer {
+        return runtime.block_on(MockServer::builder("hunter2").response("list", "There are 0 of a max of 20 players online: ").start()).unwrap();
+    }
+
+    #[test]
+    fn connect_send_and_free() {
+        let runtime = Runtime::new().unwrap();
+        let server = start_server(&runtime);
+        let addr = CString::new(server.get_address().to_string()).unwrap();
+        let password = CString::new("hunter2").unwrap();
+        let command = CString::new("list").unwrap();
+        unsafe {
+            let client = rcon_connect(addr.as_ptr(), password.as_ptr());
+            assert!(!client.is_null());
+            let response = rcon_send(client, command.as_ptr());
+            assert!(!response.is_null());
+            assert_eq!(CStr::from_ptr(response).to_str().unwrap(), "There are 0 of a max of 20 players online: ");
+            rcon_string_free(response);
+            rcon_free(client);
+        }
+    }
+
+    #[test]
+    fn wrong_password() {
+        let runtime = Runtime::new().unwrap();
+        let server = start_server(&runtime);
+        let addr = CString::new(server.get_address().to_string()).unwrap();
+        let password = CString::new("hunter3").unwrap();
+        unsafe {
+            assert!(rcon_connect(addr.as_ptr(), password.as_ptr()).is_null());
+        }
+        assert!(last_error().starts_with("could not authenticate"));
+    }
+
+    #[test]
+    fn null_arguments() {
+        let password = CString::new("hunter2").unwrap();
+        let command = CString::new("list").unwrap();
+        unsafe {
+            assert!(rcon_connect(ptr::null(), password.as_ptr()).is_null());
+            assert_eq!(last_error(), "addr is null");
+            assert!(rcon_send(ptr::null_mut(), command.as_ptr()).is_null());
+            assert_eq!(last_error(), "client is null");
+            rcon_string_free(ptr::null_mut());
+            rcon_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        let result = catch(ptr::null_mut::<c_char>(), || panic!("boom"));
+        assert!(result.is_null());
+        assert_eq!(last_error(), "panic: boom");
+    }
+}
@@ -0,0 +1,113 @@
+// parsers for ARK: Survival Evolved command output
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArkPlayer {
+    pub index: u32,
+    pub name: String,
+    pub id: String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    // SERVER for messages sent through rcon
+    pub sender: String,
+    pub character: Option<String>,
+    pub message: String
+}
+
+// "0. Alice, 76561198000000000" per line or "No Players Connected",
+// the pages of a long list are joined by the client with Dialect::Ark
+pub fn parse_list_players(output:&str) -> Option<Vec<ArkPlayer>> {
+    let output = output.trim();
+    if output.starts_with("No Players Connected") {
+        return Some(Vec::new());
+    }
+    let mut players = Vec::new();
+    for line in output.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let (index, rest) = line.split_once(". ")?;
+        let (name, id) = rest.rsplit_once(',')?;
+        players.push(ArkPlayer {
+            index: index.parse().ok()?,
+            name: name.trim().to_string(),
+            id: id.trim().to_string()
+        });
+    }
+    return Some(players);
+}
+
+// "Alice (Survivor): hello" for players, "SERVER: hello" for rcon broadcasts
+pub fn parse_get_chat(output:&str) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    for line in output.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let (sender, message) = match line.split_once(": ") {
+            Some(parts) => parts,
+            None => continue
+        };
+        let (sender, character) = match sender.rsplit_once(" (") {
+            Some((sender, character)) if character.ends_with(')') => (sender, Some(character.trim_end_matches(')').to_string())),
+            _ => (sender, None)
+        };
+        messages.push(ChatMessage {
+            sender: sender.to_string(),
+            character: character,
+            message: message.to_string()
+        });
+    }
+    return messages;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_players_connected() {
+        assert_eq!(parse_list_players("No Players Connected \n"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn list_players() {
+        let players = parse_list_players("\n0. Alice, 76561198000000001\n1. Bob, 76561198000000002\n ").unwrap();
+        assert_eq!(players, vec![
+            ArkPlayer { index: 0, name: "Alice".to_string(), id: "76561198000000001".to_string() },
+            ArkPlayer { index: 1, name: "Bob".to_string(), id: "76561198000000002".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn joined_pages() {
+        // the bodies of two packets as Termination::Timeout concatenates them, the page ends after Bob
+        let output = "\n0. Alice, 76561198000000001\n1. Bob, 76561198000000002\n2. Carol, 76561198000000003\n3. Dave, 76561198000000004\n ";
+        let players = parse_list_players(output).unwrap();
+        assert_eq!(players.len(), 4);
+        assert_eq!(players[3], ArkPlayer { index: 3, name: "Dave".to_string(), id: "76561198000000004".to_string() });
+    }
+
+    #[test]
+    fn name_with_comma() {
+        let players = parse_list_players("0. Smith, John, 76561198000000001").unwrap();
+        assert_eq!(players[0].name, "Smith, John");
+        assert_eq!(players[0].id, "76561198000000001");
+    }
+
+    #[test]
+    fn name_with_period() {
+        let players = parse_list_players("12. Dr. Who, 76561198000000001").unwrap();
+        assert_eq!(players[0].index, 12);
+        assert_eq!(players[0].name, "Dr. Who");
+    }
+
+    #[test]
+    fn get_chat() {
+        let messages = parse_get_chat("SERVER: restarting in 5 minutes\nAlice (Survivor): ok: saving now\n");
+        assert_eq!(messages, vec![
+            ChatMessage { sender: "SERVER".to_string(), character: None, message: "restarting in 5 minutes".to_string() },
+            ChatMessage { sender: "Alice".to_string(), character: Some("Survivor".to_string()), message: "ok: saving now".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn get_chat_empty() {
+        assert!(parse_get_chat("Server received, But no response!! \n").is_empty());
+    }
+}
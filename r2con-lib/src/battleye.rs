@@ -0,0 +1,185 @@
+use std::{error::{self, Error}, fmt, net::SocketAddr, time::{Duration, Instant}};
+use tokio::{io, net::{lookup_host, ToSocketAddrs, UdpSocket}, time::timeout};
+
+use crate::rcon::RconAuthError;
+
+const HEADER: &[u8; 2] = b"BE";
+const PAYLOAD_START: u8 = 0xFF;
+const LOGIN: u8 = 0x00;
+const COMMAND: u8 = 0x01;
+const SERVER_MESSAGE: u8 = 0x02;
+const MULTIPART_MARKER: u8 = 0x00;
+const MAX_DATAGRAM_SIZE: usize = 65507;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_ATTEMPTS: usize = 3;
+
+// the server drops clients that stay silent for 45 seconds
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct BattlEyeError{
+    message:String
+}
+
+impl fmt::Display for BattlEyeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "battleye error: {}", self.message)
+    }
+}
+
+impl error::Error for BattlEyeError {}
+
+impl BattlEyeError {
+    fn new(message:&str) -> BattlEyeError {
+        return BattlEyeError { message: message.to_string() };
+    }
+}
+
+fn encode(p_type:u8, payload:&[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(payload.len()+2);
+    body.push(PAYLOAD_START);
+    body.push(p_type);
+    body.extend_from_slice(payload);
+    let checksum = crc32fast::hash(&body);
+    let mut packet = Vec::with_capacity(body.len()+6);
+    packet.extend_from_slice(HEADER);
+    packet.extend_from_slice(&checksum.to_le_bytes());
+    packet.extend_from_slice(&body);
+    return packet;
+}
+
+fn decode(buf:&[u8]) -> Result<(u8, &[u8]), BattlEyeError> {
+    if buf.len() < 8 || &buf[0..2] != HEADER || buf[6] != PAYLOAD_START {
+        return Err(BattlEyeError::new("malformed packet header"));
+    }
+    let checksum = u32::from_le_bytes([buf[2], buf[3], buf[4], buf[5]]);
+    if crc32fast::hash(&buf[6..]) != checksum {
+        return Err(BattlEyeError::new("checksum mismatch"));
+    }
+    Ok((buf[7], &buf[8..]))
+}
+
+pub struct BattlEyeClient {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    sequence: u8,
+    last_sent: Instant
+}
+
+impl BattlEyeClient {
+    pub async fn connect<A: ToSocketAddrs>(addr:A, password:&str) -> Result<BattlEyeClient, Box<dyn Error + Send + Sync>> {
+        let addr = lookup_host(addr).await?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not resolve address"))?;
+        let bind_addr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        socket.connect(addr).await?;
+        let mut client = BattlEyeClient {
+            socket: socket,
+            addr: addr,
+            sequence: 0,
+            last_sent: Instant::now()
+        };
+        client.login(password).await?;
+        return Ok(client);
+    }
+
+    pub fn get_address(&self) -> io::Result<SocketAddr> {
+        return Ok(self.addr);
+    }
+
+    pub fn needs_keepalive(&self) -> bool {
+        return self.last_sent.elapsed() >= KEEPALIVE_INTERVAL;
+    }
+
+    pub async fn keepalive(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.send_command("").await?;
+        Ok(())
+    }
+
+    pub async fn send_command(&mut self, command:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        let mut payload = Vec::with_capacity(command.len()+1);
+        payload.push(sequence);
+        payload.extend_from_slice(command.as_bytes());
+        let packet = encode(COMMAND, &payload);
+
+        // datagrams can get lost, resend with the same sequence number
+        for _ in 0..MAX_ATTEMPTS {
+            self.socket.send(&packet).await?;
+            self.last_sent = Instant::now();
+            if let Ok(result) = timeout(RESPONSE_TIMEOUT, self.read_response(sequence)).await {
+                return result;
+            }
+        }
+        Err(BattlEyeError::new("no response from server").into())
+    }
+
+    async fn login(&mut self, password:&str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let packet = encode(LOGIN, password.as_bytes());
+        for _ in 0..MAX_ATTEMPTS {
+            self.socket.send(&packet).await?;
+            self.last_sent = Instant::now();
+            if let Ok(result) = timeout(RESPONSE_TIMEOUT, self.read_login()).await {
+                if result? {
+                    return Ok(());
+                }
+                return Err(RconAuthError { addr: self.addr }.into());
+            }
+        }
+        Err(BattlEyeError::new("no response from server").into())
+    }
+
+    async fn read_login(&mut self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let n = self.socket.recv(&mut buf).await?;
+            if let Ok((LOGIN, payload)) = decode(&buf[..n]) {
+                return Ok(payload.first() == Some(&1));
+            }
+        }
+    }
+
+    async fn read_response(&mut self, sequence:u8) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut parts: Vec<Option<Vec<u8>>> = Vec::new();
+        loop {
+            let n = self.socket.recv(&mut buf).await?;
+            let (p_type, payload) = match decode(&buf[..n]) {
+                Ok(packet) => packet,
+                Err(_) => {continue;} // skip corrupted packets
+            };
+            match p_type {
+                SERVER_MESSAGE => {
+                    // server messages must be acknowledged or the server drops us
+                    if let Some(server_sequence) = payload.first() {
+                        self.socket.send(&encode(SERVER_MESSAGE, &[*server_sequence])).await?;
+                    }
+                }
+                COMMAND if payload.first() == Some(&sequence) => {
+                    let body = &payload[1..];
+                    if body.len() >= 3 && body[0] == MULTIPART_MARKER {
+                        let total = usize::from(body[1]);
+                        let index = usize::from(body[2]);
+                        // a part that disagrees with the first one about the count is as
+                        // unusable as a corrupted packet
+                        if total == 0 || (!parts.is_empty() && total != parts.len()) || index >= total {
+                            continue;
+                        }
+                        if parts.is_empty() {
+                            parts = vec![None; total];
+                        }
+                        parts[index] = Some(body[3..].to_vec());
+                        if parts.iter().all(|part| part.is_some()) {
+                            let response = parts.into_iter().flatten().flatten().collect::<Vec<u8>>();
+                            return Ok(String::from_utf8_lossy(&response).into_owned());
+                        }
+                    } else {
+                        return Ok(String::from_utf8_lossy(body).into_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
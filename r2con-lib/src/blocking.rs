@@ -0,0 +1,89 @@
+use std::{error::Error, net::SocketAddr, time::Duration};
+use tokio::{io, net::ToSocketAddrs, runtime::{Builder, Runtime}};
+
+use crate::{config::RconConfig, encoding::Encoding, rcon, stats::ClientStats};
+
+// synchronous wrapper driving the async client on its own current-thread runtime
+pub struct RconClient {
+    // dropped before the runtime so the socket is closed outside of it
+    inner: rcon::RconClient,
+    runtime: Runtime
+}
+
+fn runtime() -> io::Result<Runtime> {
+    return Builder::new_current_thread().enable_all().build();
+}
+
+impl RconClient {
+    pub fn connect<A: ToSocketAddrs>(addr:A, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(rcon::RconClient::connect(addr, password))?;
+        return Ok(RconClient { inner: inner, runtime: runtime });
+    }
+
+    pub fn connect_with_config(config:&RconConfig, addr:&str, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(config.connect(addr, password))?;
+        return Ok(RconClient { inner: inner, runtime: runtime });
+    }
+
+    pub fn get_address(&self) -> io::Result<SocketAddr> {
+        return self.inner.get_address();
+    }
+
+    pub fn close(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.close());
+    }
+
+    pub fn reconnect(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.reconnect());
+    }
+
+    pub fn reconnect_with_password(&mut self, password:&str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.reconnect_with_password(password));
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        return self.inner.stats();
+    }
+
+    pub fn set_encoding(&mut self, encoding:Encoding) {
+        self.inner.set_encoding(encoding);
+    }
+
+    pub fn set_packet_delay(&mut self, delay:Duration) {
+        self.inner.set_packet_delay(delay);
+    }
+
+    pub fn set_max_response_size(&mut self, max:Option<usize>) {
+        self.inner.set_max_response_size(max);
+    }
+
+    pub fn set_command_timeout(&mut self, timeout:Option<Duration>) {
+        self.inner.set_command_timeout(timeout);
+    }
+
+    pub fn send_command(&mut self, command:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.send_command(command));
+    }
+
+    pub fn send_command_raw(&mut self, command:&str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.send_command_raw(command));
+    }
+
+    pub fn send_command_with_timeout(&mut self, command:&str, deadline:Duration) -> Result<String, Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.send_command_with_timeout(command, deadline));
+    }
+
+    pub fn send_commands(&mut self, commands:&[&str]) -> Vec<Result<String, Box<dyn Error + Send + Sync>>> {
+        return self.runtime.block_on(self.inner.send_commands(commands));
+    }
+
+    pub fn health_check(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        return self.runtime.block_on(self.inner.health_check());
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        return self.runtime.block_on(self.inner.is_alive());
+    }
+}
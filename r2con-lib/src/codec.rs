@@ -0,0 +1,51 @@
+use bytes::BytesMut;
+use tokio::io;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::rcon_packet::Packet;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RconCodec;
+
+impl RconCodec {
+    pub fn new() -> RconCodec {
+        return RconCodec;
+    }
+}
+
+impl Decoder for RconCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, io::Error> {
+        Packet::deserialize(src).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, io::Error> {
+        match self.decode(src)? {
+            Some(packet) => Ok(Some(packet)),
+            None => {
+                Packet::check_eof(src).map_err(|e| io::Error::new(io::ErrorKind::UnexpectedEof, e))?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Encoder<&Packet> for RconCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &Packet, dst: &mut BytesMut) -> Result<(), io::Error> {
+        // dst is the framed write buffer, which is kept between packets
+        item.serialize_into(dst);
+        Ok(())
+    }
+}
+
+impl Encoder<Packet> for RconCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Packet, dst: &mut BytesMut) -> Result<(), io::Error> {
+        self.encode(&item, dst)
+    }
+}
@@ -0,0 +1,279 @@
+use std::{error::Error, sync::Arc, time::Duration};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use std::net::SocketAddr;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use tokio::net::TcpStream;
+#[cfg(all(feature = "secrecy", feature = "tokio", not(target_arch = "wasm32")))]
+use secrecy::{ExposeSecret, SecretString};
+
+use tokio::sync::broadcast;
+
+use crate::{dialect::Dialect, encoding::Encoding, events::ConnectionEvent, formatting::ColorMode, ids::IdSource, retry::RetryPolicy, rcon_packet::MAX_PAYLOAD_SIZE};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use crate::{happy_eyeballs, proxy::{ProxyError, Socks5Config}, rcon::{Endpoint, RconClient}};
+
+// minecraft closes connections that send packets back to back
+pub const DEFAULT_PACKET_DELAY: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Termination {
+    // send an empty RESPONSE_VALUE after each request and read until it is echoed
+    #[default]
+    DummyPacket,
+    // collect packets until none arrive for the given duration
+    Timeout(Duration),
+    // the first response packet is the whole response
+    SinglePacket
+}
+
+#[derive(Debug, Clone)]
+pub struct RconConfig {
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    proxy: Option<Socks5Config>,
+    // tried in order when the address passed to connect fails
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    fallbacks: Vec<String>,
+    // sets TCP_NODELAY so small command packets are not held back by Nagle's algorithm
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    pub(crate) tcp_nodelay: bool,
+    pub(crate) encoding: Encoding,
+    // longer commands fail with PayloadTooLargeError, servers run every packet as a command of its own
+    pub(crate) max_payload_size: usize,
+    pub(crate) termination: Termination,
+    pub(crate) packet_delay: Duration,
+    pub(crate) id_source: Option<Arc<dyn IdSource>>,
+    pub(crate) command_timeout: Option<Duration>,
+    pub(crate) max_response_size: Option<usize>,
+    // keep the password so reconnect() can log in again
+    pub(crate) store_credentials: bool,
+    pub(crate) max_commands_per_second: Option<u32>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    #[cfg(feature = "dns")]
+    resolve_srv: bool,
+    pub(crate) events: Option<broadcast::Sender<ConnectionEvent>>,
+    pub(crate) keepalive_interval: Option<Duration>,
+    // placeholder some servers send instead of an empty response
+    pub(crate) empty_response: Option<String>,
+    pub(crate) dialect: Dialect,
+    pub(crate) color_mode: ColorMode
+}
+
+impl Default for RconConfig {
+    fn default() -> RconConfig {
+        return RconConfig {
+            #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+            proxy: None,
+            #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+            fallbacks: Vec::new(),
+            #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+            tcp_nodelay: false,
+            encoding: Encoding::default(),
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            termination: Termination::default(),
+            packet_delay: DEFAULT_PACKET_DELAY,
+            id_source: None,
+            command_timeout: None,
+            max_response_size: None,
+            store_credentials: true,
+            max_commands_per_second: None,
+            retry_policy: None,
+            #[cfg(feature = "dns")]
+            resolve_srv: false,
+            events: None,
+            keepalive_interval: None,
+            empty_response: None,
+            dialect: Dialect::default(),
+            color_mode: ColorMode::default()
+        };
+    }
+}
+
+impl RconConfig {
+    pub fn new() -> RconConfig {
+        return RconConfig::default();
+    }
+
+    // replaces every setting bundled in DialectSettings, builder calls after it still override them
+    pub fn dialect(self, dialect:Dialect) -> RconConfig {
+        return dialect.apply(self);
+    }
+
+    pub fn encoding(mut self, encoding:Encoding) -> RconConfig {
+        self.encoding = encoding;
+        return self;
+    }
+
+    pub fn max_payload_size(mut self, size:usize) -> RconConfig {
+        self.max_payload_size = size;
+        return self;
+    }
+
+    pub fn termination(mut self, termination:Termination) -> RconConfig {
+        self.termination = termination;
+        return self;
+    }
+
+    pub fn packet_delay(mut self, delay:Duration) -> RconConfig {
+        self.packet_delay = delay;
+        return self;
+    }
+
+    pub fn id_source<S: IdSource + 'static>(mut self, id_source:S) -> RconConfig {
+        self.id_source = Some(Arc::new(id_source));
+        return self;
+    }
+
+    pub fn command_timeout(mut self, timeout:Duration) -> RconConfig {
+        self.command_timeout = Some(timeout);
+        return self;
+    }
+
+    pub fn max_response_size(mut self, max:usize) -> RconConfig {
+        self.max_response_size = Some(max);
+        return self;
+    }
+
+    // 0 disables the limit
+    pub fn max_commands_per_second(mut self, rate:u32) -> RconConfig {
+        self.max_commands_per_second = match rate {
+            0 => None,
+            rate => Some(rate)
+        };
+        return self;
+    }
+
+    // retried commands reconnect first, so the password has to be stored
+    pub fn retry_policy(mut self, policy:RetryPolicy) -> RconConfig {
+        self.retry_policy = Some(policy);
+        return self;
+    }
+
+    // lets subscribers see the events fired while connecting, RconClient::subscribe only sees later ones
+    pub fn events(mut self, events:broadcast::Sender<ConnectionEvent>) -> RconConfig {
+        self.events = Some(events);
+        return self;
+    }
+
+    // see RconClient::needs_keepalive
+    pub fn keepalive_interval(mut self, interval:Duration) -> RconConfig {
+        self.keepalive_interval = Some(interval);
+        return self;
+    }
+
+    pub fn empty_response(mut self, placeholder:&str) -> RconConfig {
+        self.empty_response = Some(placeholder.to_string());
+        return self;
+    }
+
+    pub fn store_credentials(mut self, store:bool) -> RconConfig {
+        self.store_credentials = store;
+        return self;
+    }
+
+    // applied to decoded responses, the raw byte methods are left untouched
+    pub fn color_mode(mut self, mode:ColorMode) -> RconConfig {
+        self.color_mode = mode;
+        return self;
+    }
+
+    pub(crate) fn decode(&self, bytes:&[u8]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let text = self.encoding.decode(bytes)?;
+        return match self.color_mode {
+            ColorMode::Keep => Ok(text),
+            mode => Ok(mode.apply(&text))
+        };
+    }
+}
+
+// browsers can only open WebSockets, see websocket::connect, elsewhere connecting needs tokio
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+impl RconConfig {
+    pub fn proxy(mut self, proxy:Socks5Config) -> RconConfig {
+        self.proxy = Some(proxy);
+        return self;
+    }
+
+    // hosts given without a port are looked up like the minecraft client does
+    #[cfg(feature = "dns")]
+    pub fn resolve_srv(mut self, resolve:bool) -> RconConfig {
+        self.resolve_srv = resolve;
+        return self;
+    }
+
+    pub fn tcp_nodelay(mut self, nodelay:bool) -> RconConfig {
+        self.tcp_nodelay = nodelay;
+        return self;
+    }
+
+    pub fn fallback(mut self, addr:&str) -> RconConfig {
+        self.fallbacks.push(addr.to_string());
+        return self;
+    }
+
+    pub fn fallbacks(mut self, addrs:&[&str]) -> RconConfig {
+        self.fallbacks.extend(addrs.iter().map(|addr| addr.to_string()));
+        return self;
+    }
+
+    // the client's get_address tells which of the addresses was used
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, password)))]
+    pub async fn connect(&self, addr:&str, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let mut result = self.connect_to(addr, password).await;
+        for fallback in &self.fallbacks {
+            if result.is_ok() {
+                break;
+            }
+            result = self.connect_to(fallback, password).await;
+        }
+        result
+    }
+
+    async fn connect_to(&self, addr:&str, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        #[cfg(feature = "dns")]
+        let srv_addr = match self.resolve_srv && split_host_port(addr).is_err() {
+            true => crate::dns::resolve_minecraft_addr(addr).await?,
+            false => addr.to_string()
+        };
+        #[cfg(feature = "dns")]
+        let addr = srv_addr.as_str();
+        let (stream, peer_addr) = self.dial(addr).await?;
+        let endpoint = match self.proxy {
+            Some(_) => Endpoint::Proxy(addr.to_string()),
+            None => Endpoint::Tcp(peer_addr)
+        };
+        return RconClient::connect_with_config(stream, peer_addr, password, self.clone(), endpoint).await;
+    }
+
+    #[cfg(feature = "secrecy")]
+    pub async fn connect_secret(&self, addr:&str, password:&SecretString) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        return self.connect(addr, password.expose_secret()).await;
+    }
+
+    pub(crate) async fn dial(&self, addr:&str) -> Result<(TcpStream, SocketAddr), Box<dyn Error + Send + Sync>> {
+        if let Some(proxy) = &self.proxy {
+            let (host, port) = split_host_port(addr)?;
+            let stream = proxy.connect(host, port).await?;
+            stream.set_nodelay(self.tcp_nodelay)?;
+            // the peer of a proxied stream is the proxy itself
+            let peer_addr = match addr.parse::<SocketAddr>() {
+                Ok(target) => target,
+                Err(_) => stream.peer_addr()?
+            };
+            return Ok((stream, peer_addr));
+        }
+        let stream = happy_eyeballs::connect(addr).await?;
+        stream.set_nodelay(self.tcp_nodelay)?;
+        let peer_addr = stream.peer_addr()?;
+        return Ok((stream, peer_addr));
+    }
+}
+
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+fn split_host_port(addr:&str) -> Result<(&str, u16), Box<dyn Error + Send + Sync>> {
+    if let Some((host, port)) = addr.rsplit_once(':') {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = port.parse::<u16>()?;
+        return Ok((host, port));
+    }
+    Err(ProxyError::new("address must be in the form host:port").into())
+}
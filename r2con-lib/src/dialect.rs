@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use crate::{config::{RconConfig, Termination, DEFAULT_PACKET_DELAY}, encoding::Encoding, rcon_packet::MAX_PAYLOAD_SIZE};
+
+// everything that differs between games, see Dialect::settings for the presets
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialectSettings {
+    pub termination: Termination,
+    pub packet_delay: Duration,
+    pub encoding: Encoding,
+    pub max_payload_size: usize,
+    pub command_timeout: Option<Duration>,
+    pub keepalive_interval: Option<Duration>,
+    // placeholder the server sends instead of an empty response
+    pub empty_response: Option<String>
+}
+
+impl Default for DialectSettings {
+    fn default() -> DialectSettings {
+        return DialectSettings {
+            termination: Termination::DummyPacket,
+            packet_delay: DEFAULT_PACKET_DELAY,
+            encoding: Encoding::Lossy,
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            command_timeout: None,
+            keepalive_interval: None,
+            empty_response: None
+        };
+    }
+}
+
+// per-game quirks, applied on top of an RconConfig
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Dialect {
+    #[default]
+    Minecraft,
+    // srcds accepts larger requests and back to back packets
+    Source,
+    // drops clients that send empty RESPONSE_VALUE packets, so the dummy terminator is
+    // skipped and every response is expected in a single packet
+    Factorio,
+    // spreads long output like ListPlayers over several packets without a way to tell the
+    // last one, drops idle connections and replies with a placeholder instead of an empty response
+    Ark,
+    // truncates multi-packet responses, rejects the dummy terminator and leaves some
+    // commands unanswered, so the first packet is the whole response and reads time out
+    Palworld,
+    Custom(DialectSettings)
+}
+
+// 4096 byte packets minus the id, type and terminators
+pub const SOURCE_MAX_PAYLOAD_SIZE: usize = 4086;
+// the server drops connections after 60 seconds without a command
+pub const ARK_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(45);
+// pages of a response arrive back to back, a pause this long ends it
+pub const ARK_RESPONSE_IDLE: Duration = Duration::from_millis(300);
+pub const ARK_EMPTY_RESPONSE: &str = "Server received, But no response!!";
+pub const PALWORLD_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Dialect {
+    pub fn settings(&self) -> DialectSettings {
+        let defaults = DialectSettings::default();
+        match self {
+            Dialect::Minecraft => defaults,
+            Dialect::Source => DialectSettings {
+                packet_delay: Duration::ZERO,
+                max_payload_size: SOURCE_MAX_PAYLOAD_SIZE,
+                ..defaults
+            },
+            Dialect::Factorio => DialectSettings {
+                termination: Termination::SinglePacket,
+                packet_delay: Duration::ZERO,
+                ..defaults
+            },
+            Dialect::Ark => DialectSettings {
+                termination: Termination::Timeout(ARK_RESPONSE_IDLE),
+                packet_delay: Duration::ZERO,
+                keepalive_interval: Some(ARK_KEEPALIVE_INTERVAL),
+                empty_response: Some(ARK_EMPTY_RESPONSE.to_string()),
+                ..defaults
+            },
+            Dialect::Palworld => DialectSettings {
+                termination: Termination::SinglePacket,
+                packet_delay: Duration::ZERO,
+                command_timeout: Some(PALWORLD_COMMAND_TIMEOUT),
+                ..defaults
+            },
+            Dialect::Custom(settings) => settings.clone()
+        }
+    }
+
+    pub(crate) fn apply(&self, mut config:RconConfig) -> RconConfig {
+        let settings = self.settings();
+        config.termination = settings.termination;
+        config.packet_delay = settings.packet_delay;
+        config.encoding = settings.encoding;
+        config.max_payload_size = settings.max_payload_size;
+        config.command_timeout = settings.command_timeout;
+        config.keepalive_interval = settings.keepalive_interval;
+        config.empty_response = settings.empty_response;
+        config.dialect = self.clone();
+        return config;
+    }
+}
@@ -0,0 +1,41 @@
+use std::{cmp::Reverse, error::{self, Error}, fmt};
+use hickory_resolver::{error::ResolveErrorKind, TokioAsyncResolver};
+
+#[derive(Debug, Clone)]
+pub struct DnsError{
+    host:String
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no SRV record found for {} and no port given", self.host)
+    }
+}
+
+impl error::Error for DnsError {}
+
+// picks the record the minecraft client would use: lowest priority, then highest weight
+pub async fn resolve_srv(name:&str) -> Result<Option<(String, u16)>, Box<dyn Error + Send + Sync>> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+    let lookup = match resolver.srv_lookup(name).await {
+        Ok(lookup) => lookup,
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound{..}) => return Ok(None),
+        Err(e) => return Err(e.into())
+    };
+    let record = lookup.iter().min_by_key(|record| (record.priority(), Reverse(record.weight())));
+    return Ok(record.map(|record| {
+        let target = record.target().to_utf8();
+        (target.trim_end_matches('.').to_string(), record.port())
+    }));
+}
+
+pub(crate) async fn resolve_minecraft_addr(host:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    return match resolve_minecraft_srv(host).await? {
+        Some((target, port)) => Ok(format!("{}:{}", target, port)),
+        None => Err(DnsError{host:host.to_string()}.into())
+    };
+}
+
+pub async fn resolve_minecraft_srv(host:&str) -> Result<Option<(String, u16)>, Box<dyn Error + Send + Sync>> {
+    return resolve_srv(&format!("_minecraft._tcp.{}", host)).await;
+}
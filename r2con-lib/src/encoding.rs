@@ -0,0 +1,32 @@
+use std::error::Error;
+
+// windows-1252 code points for 0x80..=0x9F, undefined bytes map to their C1 control
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    #[default]
+    Lossy,
+    Latin1,
+    Cp1252
+}
+
+impl Encoding {
+    pub fn decode(&self, bytes:&[u8]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        return match self {
+            Encoding::Utf8 => Ok(String::from_utf8(bytes.to_vec())?),
+            Encoding::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            Encoding::Latin1 => Ok(bytes.iter().map(|b| char::from(*b)).collect()),
+            Encoding::Cp1252 => Ok(bytes.iter().map(|b| match b {
+                0x80..=0x9F => CP1252_HIGH[usize::from(b - 0x80)],
+                _ => char::from(*b)
+            }).collect())
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use futures_util::{stream, Stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::rcon_packet::Packet;
+
+// events are dropped for subscribers lagging further behind than this
+pub const EVENT_CAPACITY: usize = 16;
+// same for unsolicited packets, servers relaying chat or logs can send bursts of them
+pub const UNSOLICITED_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEvent {
+    Connected,
+    Authenticated,
+    Disconnected(String),
+    // number of the reconnection attempt, starting at 1
+    Reconnecting(u32)
+}
+
+pub(crate) fn emit(events:&broadcast::Sender<ConnectionEvent>, event:ConnectionEvent) {
+    // sending only fails when nobody is subscribed
+    let _ = events.send(event);
+}
+
+// skips whatever a lagging subscriber missed and ends once every sender is dropped
+pub(crate) fn packet_stream(rx:broadcast::Receiver<Packet>) -> impl Stream<Item = Packet> {
+    return stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(packet) => return Some((packet, rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None
+            }
+        }
+    });
+}
@@ -0,0 +1,134 @@
+// minecraft prefixes colors and styles with a section sign, source engine games use control bytes
+const SECTION_SIGN: char = '§';
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorMode {
+    // leave responses as the server sent them
+    #[default]
+    Keep,
+    Strip,
+    // convert to ANSI escapes for terminals
+    Ansi
+}
+
+impl ColorMode {
+    pub fn apply(&self, text:&str) -> String {
+        return match self {
+            ColorMode::Keep => text.to_string(),
+            ColorMode::Strip => strip_codes(text),
+            ColorMode::Ansi => to_ansi(text)
+        };
+    }
+}
+
+// bytes used as chat colors by source games, tab, newline and carriage return are left alone
+fn is_source_color(c:char) -> bool {
+    return matches!(c, '\x01'..='\x08' | '\x0B' | '\x0C' | '\x0E'..='\x10');
+}
+
+pub fn strip_codes(text:&str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == SECTION_SIGN {
+            // the code after the sign, hex colors are a chain of such pairs
+            chars.next();
+        } else if !is_source_color(c) {
+            result.push(c);
+        }
+    }
+    return result;
+}
+
+pub fn to_ansi(text:&str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut styled = false;
+    while let Some(c) = chars.next() {
+        let escape = if c == SECTION_SIGN {
+            match chars.next() {
+                Some('x') | Some('X') => hex_color(&mut chars),
+                Some(code) => minecraft_escape(code),
+                None => None
+            }
+        } else if is_source_color(c) {
+            source_escape(c)
+        } else {
+            result.push(c);
+            continue;
+        };
+        if let Some(escape) = escape {
+            styled = escape != ANSI_RESET;
+            result.push_str(&escape);
+        }
+    }
+    if styled {
+        result.push_str(ANSI_RESET);
+    }
+    return result;
+}
+
+fn minecraft_escape(code:char) -> Option<String> {
+    // a color also resets any style set before it, like in the minecraft client
+    let sgr = match code.to_ascii_lowercase() {
+        '0' => "0;30",
+        '1' => "0;34",
+        '2' => "0;32",
+        '3' => "0;36",
+        '4' => "0;31",
+        '5' => "0;35",
+        '6' => "0;33",
+        '7' => "0;37",
+        '8' => "0;90",
+        '9' => "0;94",
+        'a' => "0;92",
+        'b' => "0;96",
+        'c' => "0;91",
+        'd' => "0;95",
+        'e' => "0;93",
+        'f' => "0;97",
+        'k' => "8",
+        'l' => "1",
+        'm' => "9",
+        'n' => "4",
+        'o' => "3",
+        'r' => return Some(ANSI_RESET.to_string()),
+        _ => return None
+    };
+    return Some(format!("\x1b[{}m", sgr));
+}
+
+// §x§r§r§g§g§b§b, spigot's format for hex colors
+fn hex_color<I: Iterator<Item = char>>(chars:&mut std::iter::Peekable<I>) -> Option<String> {
+    let mut digits = String::with_capacity(6);
+    while digits.len() < 6 && chars.peek() == Some(&SECTION_SIGN) {
+        chars.next();
+        match chars.next() {
+            Some(digit) if digit.is_ascii_hexdigit() => digits.push(digit),
+            _ => return None
+        }
+    }
+    let rgb = u32::from_str_radix(&digits, 16).ok().filter(|_| digits.len() == 6)?;
+    return Some(format!("\x1b[0;38;2;{};{};{}m", rgb >> 16, (rgb >> 8) & 0xFF, rgb & 0xFF));
+}
+
+fn source_escape(code:char) -> Option<String> {
+    let sgr = match code {
+        '\x01' => return Some(ANSI_RESET.to_string()),
+        '\x02' => "31",
+        '\x03' => "35",
+        '\x04' => "32",
+        '\x05' => "33",
+        '\x06' => "92",
+        '\x07' => "91",
+        '\x08' => "90",
+        '\x0B' => "94",
+        '\x0C' => "34",
+        '\x0E' => "95",
+        '\x0F' => "91",
+        '\x10' => "33",
+        _ => return None
+    };
+    return Some(format!("\x1b[{}m", sgr));
+}
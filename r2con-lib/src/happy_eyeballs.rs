@@ -0,0 +1,61 @@
+use std::{net::SocketAddr, time::Duration};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use tokio::{io, net::{lookup_host, TcpStream, ToSocketAddrs}, time::timeout};
+
+// head start given to each attempt before the next address is tried (RFC 8305)
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+// races the resolved addresses instead of waiting for each one to time out
+pub(crate) async fn connect<A: ToSocketAddrs>(addr:A) -> io::Result<TcpStream> {
+    let mut pending = interleave(lookup_host(addr).await?.collect()).into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    if let Some(addr) = pending.next() {
+        attempts.push(TcpStream::connect(addr));
+    }
+    while !attempts.is_empty() {
+        let result = match pending.len() {
+            0 => attempts.next().await,
+            _ => match timeout(CONNECTION_ATTEMPT_DELAY, attempts.next()).await {
+                Ok(result) => result,
+                Err(_) => {
+                    if let Some(addr) = pending.next() {
+                        attempts.push(TcpStream::connect(addr));
+                    }
+                    continue;
+                }
+            }
+        };
+        match result {
+            Some(Ok(stream)) => return Ok(stream),
+            Some(Err(e)) => {
+                last_error = Some(e);
+                // a failed attempt frees its slot right away
+                if let Some(addr) = pending.next() {
+                    attempts.push(TcpStream::connect(addr));
+                }
+            }
+            None => break
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any address")))
+}
+
+// alternates address families, starting with the one the resolver preferred
+fn interleave(addrs:Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_v6 = addrs.first().is_some_and(|addr| addr.is_ipv6());
+    let (mut preferred, mut other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(|addr| addr.is_ipv6() == prefer_v6);
+    let mut result = Vec::with_capacity(preferred.len()+other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (first, second) => {
+                result.extend(first);
+                result.extend(second);
+            }
+        }
+    }
+    return result;
+}
@@ -0,0 +1,44 @@
+use std::{fmt, sync::atomic::{AtomicI32, Ordering}};
+
+// -1 is the id servers use to signal an authentication failure
+pub const RESERVED_ID: i32 = -1;
+
+pub trait IdSource: Send + Sync {
+    fn next_id(&self) -> i32;
+}
+
+impl fmt::Debug for dyn IdSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IdSource")
+    }
+}
+
+#[derive(Debug)]
+pub struct SequentialIds {
+    next: AtomicI32
+}
+
+impl SequentialIds {
+    pub const fn new() -> SequentialIds {
+        return SequentialIds { next: AtomicI32::new(1) };
+    }
+}
+
+impl Default for SequentialIds {
+    fn default() -> SequentialIds {
+        return SequentialIds::new();
+    }
+}
+
+impl IdSource for SequentialIds {
+    fn next_id(&self) -> i32 {
+        // stay in the positive range so ids never collide with RESERVED_ID
+        let previous = self.next.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| {
+            Some(if id == i32::MAX { 1 } else { id + 1 })
+        });
+        return match previous {
+            Ok(id) => id,
+            Err(id) => id
+        };
+    }
+}
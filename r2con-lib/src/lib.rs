@@ -1,5 +1,83 @@
+#[cfg(not(any(feature = "tokio", feature = "futures-io")))]
+compile_error!("r2con needs the tokio or the futures-io feature for its timers");
+
 pub mod rcon_packet;
 pub mod rcon;
+pub mod codec;
+pub mod transport;
+pub mod config;
+pub mod encoding;
+pub mod formatting;
+pub mod ids;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod proxy;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod query;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod server;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod split;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod queue;
+mod rate_limit;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+mod happy_eyeballs;
+mod runtime;
+pub mod retry;
+pub mod stats;
+pub mod response;
+pub mod events;
+pub mod dialect;
+#[cfg(all(feature = "webrcon", not(target_arch = "wasm32")))]
+pub mod webrcon;
+#[cfg(all(feature = "battleye", not(target_arch = "wasm32")))]
+pub mod battleye;
+#[cfg(all(feature = "slp", not(target_arch = "wasm32")))]
+pub mod slp;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod websocket;
+#[cfg(all(feature = "dns", not(target_arch = "wasm32")))]
+pub mod dns;
+#[cfg(feature = "minecraft")]
+pub mod minecraft;
+#[cfg(feature = "source")]
+pub mod source;
+#[cfg(feature = "ark")]
+pub mod ark;
 
 pub use rcon::RconClient;
-pub use rcon::RconAuthError;
\ No newline at end of file
+pub use rcon::RconAuthError;
+pub use rcon::ConnectionClosedError;
+pub use rcon::RconError;
+pub use codec::RconCodec;
+pub use rcon_packet::ProtocolError;
+pub use transport::Transport;
+pub use config::{RconConfig, Termination};
+pub use encoding::Encoding;
+pub use formatting::ColorMode;
+pub use ids::{IdSource, SequentialIds};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub use proxy::Socks5Config;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub use query::A2sClient;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub use server::RconServer;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub use split::CommandSender;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub use queue::CommandQueue;
+pub use stats::ClientStats;
+pub use retry::RetryPolicy;
+pub use response::CommandResponse;
+pub use events::ConnectionEvent;
+pub use dialect::{Dialect, DialectSettings};
+#[cfg(all(feature = "webrcon", not(target_arch = "wasm32")))]
+pub use webrcon::WebRconClient;
+#[cfg(all(feature = "battleye", not(target_arch = "wasm32")))]
+pub use battleye::BattlEyeClient;
+#[cfg(feature = "secrecy")]
+pub use secrecy::SecretString;
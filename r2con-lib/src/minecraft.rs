@@ -0,0 +1,389 @@
+use std::{error::{self, Error}, fmt, future::Future};
+
+use crate::{rcon::RconClient, transport::MaybeSend};
+
+// parsers for the replies of common vanilla commands, None when the text doesn't match
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    pub name: String,
+    // only present in the output of `list uuids`
+    pub uuid: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerList {
+    pub online: u32,
+    pub max: u32,
+    pub players: Vec<Player>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BanEntry {
+    pub target: String,
+    pub source: String,
+    pub reason: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataKind {
+    Entity,
+    Block,
+    Storage
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataResult {
+    pub target: String,
+    pub kind: DataKind,
+    // the SNBT value as printed by the server
+    pub value: String
+}
+
+fn split_names(names:&str) -> Vec<&str> {
+    return names.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()).collect();
+}
+
+// "There are 1 of a max of 20 players online: Alice" (1.13+) or "There are 1/20 players online:Alice"
+pub fn parse_list(output:&str) -> Option<PlayerList> {
+    let rest = output.trim().strip_prefix("There are ")?;
+    let (counts, names) = rest.split_once(':')?;
+    let counts = counts.strip_suffix(" players online")?;
+    let (online, max) = match counts.split_once(" of a max of ") {
+        Some(counts) => counts,
+        None => counts.split_once('/')?
+    };
+    let players = split_names(names).into_iter().map(|entry| {
+        match entry.split_once(" (") {
+            Some((name, uuid)) => Player { name: name.to_string(), uuid: Some(uuid.trim_end_matches(')').to_string()) },
+            None => Player { name: entry.to_string(), uuid: None }
+        }
+    }).collect();
+    return Some(PlayerList {
+        online: online.trim().parse().ok()?,
+        max: max.trim().parse().ok()?,
+        players: players
+    });
+}
+
+// "There are 2 whitelisted player(s): Alice, Bob" or "There are no whitelisted players"
+pub fn parse_whitelist(output:&str) -> Option<Vec<String>> {
+    let output = output.trim();
+    if output == "There are no whitelisted players" {
+        return Some(Vec::new());
+    }
+    let (header, names) = output.split_once(':')?;
+    if !header.starts_with("There are ") || !header.contains("whitelisted player") {
+        return None;
+    }
+    return Some(split_names(names).into_iter().map(|name| name.to_string()).collect());
+}
+
+// "There are 1 ban(s):Alice was banned by Server: Banned by an operator."
+// rcon strips the newlines between entries, the target is then taken as the trailing
+// run of name characters before " was banned by ", so ip bans only survive with newlines
+pub fn parse_banlist(output:&str) -> Option<Vec<BanEntry>> {
+    let output = output.trim();
+    if output == "There are no bans" {
+        return Some(Vec::new());
+    }
+    let rest = output.strip_prefix("There are ")?;
+    let (header, entries) = rest.split_once(':')?;
+    if !header.contains("ban") {
+        return None;
+    }
+    if entries.contains('\n') {
+        return entries.lines().filter(|line| !line.trim().is_empty()).map(|line| parse_ban_entry(line.trim())).collect();
+    }
+    let mut bans = Vec::new();
+    let mut remaining = entries;
+    let mut target = None;
+    while let Some(index) = remaining.find(" was banned by ") {
+        let (before, after) = remaining.split_at(index);
+        let name_start = before.rfind(|c:char| !(c.is_ascii_alphanumeric() || c == '_')).map_or(0, |i| i+1);
+        if let Some(target) = target.take() {
+            bans.push(finish_ban(target, &before[..name_start])?);
+        }
+        target = Some(before[name_start..].to_string());
+        remaining = &after[" was banned by ".len()..];
+    }
+    if let Some(target) = target {
+        bans.push(finish_ban(target, remaining)?);
+    }
+    // 1.12 lists only the names, "There are 2 total banned players:Alice, Bob"
+    if bans.is_empty() && !entries.trim().is_empty() {
+        return None;
+    }
+    return Some(bans);
+}
+
+fn parse_ban_entry(line:&str) -> Option<BanEntry> {
+    let (target, rest) = line.split_once(" was banned by ")?;
+    return finish_ban(target.to_string(), rest);
+}
+
+fn finish_ban(target:String, source_and_reason:&str) -> Option<BanEntry> {
+    let (source, reason) = source_and_reason.split_once(": ")?;
+    return Some(BanEntry { target: target, source: source.to_string(), reason: reason.to_string() });
+}
+
+// "Seed: [-1234567890]"
+pub fn parse_seed(output:&str) -> Option<i64> {
+    let seed = output.trim().strip_prefix("Seed: ")?;
+    return seed.trim_start_matches('[').trim_end_matches(']').parse().ok();
+}
+
+// "Alice has the following entity data: {...}"
+pub fn parse_data_get(output:&str) -> Option<DataResult> {
+    let output = output.trim();
+    for (marker, kind) in [
+        (" has the following entity data: ", DataKind::Entity),
+        (" has the following block data: ", DataKind::Block),
+        (" has the following contents: ", DataKind::Storage),
+    ] {
+        if let Some((target, value)) = output.split_once(marker) {
+            return Some(DataResult { target: target.to_string(), kind: kind, value: value.to_string() });
+        }
+    }
+    return None;
+}
+
+// replies of vanilla commands that did not do anything
+const FAILURE_PREFIXES: [&str; 8] = [
+    "Nothing changed",
+    "No player was found",
+    "That player does not exist",
+    "Unknown or incomplete command",
+    "Incorrect argument",
+    "Player is already whitelisted",
+    "Player is not whitelisted",
+    "Could not",
+];
+
+#[derive(Debug, Clone)]
+pub struct MinecraftCommandError{
+    pub command:String,
+    pub reply:String
+}
+
+impl fmt::Display for MinecraftCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' failed: {}", self.command, self.reply)
+    }
+}
+
+impl error::Error for MinecraftCommandError {}
+
+#[derive(Debug, Clone)]
+pub struct InvalidArgumentError{
+    pub argument:String
+}
+
+impl fmt::Display for InvalidArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid player name '{}'", self.argument)
+    }
+}
+
+impl error::Error for InvalidArgumentError {}
+
+// player names are up to 16 of [A-Za-z0-9_], anything else could smuggle in extra arguments
+fn player_name(name:&str) -> Result<&str, Box<dyn Error + Send + Sync>> {
+    let valid = !name.is_empty() && name.len() <= 16 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(InvalidArgumentError{argument:name.to_string()}.into());
+    }
+    return Ok(name);
+}
+
+// a newline would end the command early
+fn free_text(text:&str) -> String {
+    return text.replace(['\r', '\n'], " ");
+}
+
+fn check_reply(command:&str, reply:String) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if FAILURE_PREFIXES.iter().any(|prefix| reply.starts_with(prefix)) {
+        return Err(MinecraftCommandError{command:command.to_string(), reply:reply}.into());
+    }
+    return Ok(reply);
+}
+
+// typed helpers on top of send_command, failures reported in the reply become errors
+pub trait MinecraftCommands: MaybeSend {
+    fn run_checked(&mut self, command:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend;
+
+    fn op(&mut self, name:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("op {}", name));
+        async move { self.run_checked(&command?).await }
+    }
+
+    fn deop(&mut self, name:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("deop {}", name));
+        async move { self.run_checked(&command?).await }
+    }
+
+    fn kick(&mut self, name:&str, reason:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("kick {} {}", name, free_text(reason)));
+        async move { self.run_checked(command?.trim_end()).await }
+    }
+
+    fn ban(&mut self, name:&str, reason:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("ban {} {}", name, free_text(reason)));
+        async move { self.run_checked(command?.trim_end()).await }
+    }
+
+    fn pardon(&mut self, name:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("pardon {}", name));
+        async move { self.run_checked(&command?).await }
+    }
+
+    fn whitelist_add(&mut self, name:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("whitelist add {}", name));
+        async move { self.run_checked(&command?).await }
+    }
+
+    fn whitelist_remove(&mut self, name:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = player_name(name).map(|name| format!("whitelist remove {}", name));
+        async move { self.run_checked(&command?).await }
+    }
+
+    fn say(&mut self, message:&str) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        let command = format!("say {}", free_text(message));
+        async move { self.run_checked(&command).await }
+    }
+
+    fn save_all(&mut self) -> impl Future<Output = Result<String, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        self.run_checked("save-all")
+    }
+
+    fn list(&mut self) -> impl Future<Output = Result<PlayerList, Box<dyn Error + Send + Sync>>> + MaybeSend {
+        async move {
+            let reply = self.run_checked("list").await?;
+            match parse_list(&reply) {
+                Some(list) => Ok(list),
+                None => Err(MinecraftCommandError{command:"list".to_string(), reply:reply}.into())
+            }
+        }
+    }
+}
+
+impl MinecraftCommands for RconClient {
+    async fn run_checked(&mut self, command:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let reply = self.send_command(command).await?;
+        check_reply(command, reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ban(target:&str, source:&str, reason:&str) -> BanEntry {
+        return BanEntry { target: target.to_string(), source: source.to_string(), reason: reason.to_string() };
+    }
+
+    #[test]
+    fn list_1_13() {
+        let list = parse_list("There are 2 of a max of 20 players online: Alice, Bob").unwrap();
+        assert_eq!(list.online, 2);
+        assert_eq!(list.max, 20);
+        assert_eq!(list.players, vec![
+            Player { name: "Alice".to_string(), uuid: None },
+            Player { name: "Bob".to_string(), uuid: None }
+        ]);
+    }
+
+    #[test]
+    fn list_uuids() {
+        let list = parse_list("There are 1 of a max of 20 players online: Alice (069a79f4-44e9-4726-a5be-fca90e38aaf5)").unwrap();
+        assert_eq!(list.players, vec![Player { name: "Alice".to_string(), uuid: Some("069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string()) }]);
+    }
+
+    #[test]
+    fn list_empty() {
+        let list = parse_list("There are 0 of a max of 20 players online: ").unwrap();
+        assert_eq!(list.online, 0);
+        assert!(list.players.is_empty());
+    }
+
+    #[test]
+    fn list_1_12() {
+        let list = parse_list("There are 2/20 players online:Alice, Bob").unwrap();
+        assert_eq!((list.online, list.max), (2, 20));
+        assert_eq!(list.players.len(), 2);
+        assert_eq!(list.players[1].name, "Bob");
+    }
+
+    #[test]
+    fn list_not_matching() {
+        assert_eq!(parse_list("Unknown or incomplete command, see below for error"), None);
+    }
+
+    #[test]
+    fn whitelist() {
+        assert_eq!(parse_whitelist("There are 2 whitelisted players: Alice, Bob"), Some(vec!["Alice".to_string(), "Bob".to_string()]));
+        assert_eq!(parse_whitelist("There are no whitelisted players"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn whitelist_1_12() {
+        assert_eq!(parse_whitelist("There are 2 (out of 3 seen) whitelisted players:Alice, Bob"), Some(vec!["Alice".to_string(), "Bob".to_string()]));
+    }
+
+    #[test]
+    fn banlist_with_newlines() {
+        let bans = parse_banlist("There are 2 ban(s):\nAlice was banned by Server: Banned by an operator.\n127.0.0.1 was banned by Steve: griefing").unwrap();
+        assert_eq!(bans, vec![
+            ban("Alice", "Server", "Banned by an operator."),
+            ban("127.0.0.1", "Steve", "griefing")
+        ]);
+    }
+
+    #[test]
+    fn banlist_without_newlines() {
+        let bans = parse_banlist("There are 2 ban(s):Alice was banned by Server: Banned by an operator.Bob was banned by Steve: griefing, again").unwrap();
+        assert_eq!(bans, vec![
+            ban("Alice", "Server", "Banned by an operator."),
+            ban("Bob", "Steve", "griefing, again")
+        ]);
+    }
+
+    #[test]
+    fn banlist_reason_ending_in_name_character() {
+        // without the newline nothing separates "griefing" from "Bob", both end up in the target
+        let bans = parse_banlist("There are 2 ban(s):Alice was banned by Steve: griefingBob was banned by Server: Banned by an operator.").unwrap();
+        assert_eq!(bans, vec![
+            ban("Alice", "Steve", ""),
+            ban("griefingBob", "Server", "Banned by an operator.")
+        ]);
+    }
+
+    #[test]
+    fn banlist_empty() {
+        assert_eq!(parse_banlist("There are no bans"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn banlist_1_12() {
+        assert_eq!(parse_banlist("There are 2 total banned players:Alice, Bob"), None);
+        assert_eq!(parse_banlist("There are 0 total banned players:"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn seed() {
+        assert_eq!(parse_seed("Seed: [-1234567890]"), Some(-1234567890));
+        assert_eq!(parse_seed("Seed: 4622716516375425358"), Some(4622716516375425358));
+        assert_eq!(parse_seed("Unknown or incomplete command, see below for error"), None);
+    }
+
+    #[test]
+    fn data_get() {
+        let data = parse_data_get("Alice has the following entity data: {Health: 20.0f, foodLevel: 20}").unwrap();
+        assert_eq!(data, DataResult { target: "Alice".to_string(), kind: DataKind::Entity, value: "{Health: 20.0f, foodLevel: 20}".to_string() });
+        let data = parse_data_get("0, 64, 0 has the following block data: {Items: []}").unwrap();
+        assert_eq!((data.target.as_str(), data.kind), ("0, 64, 0", DataKind::Block));
+        let data = parse_data_get("Storage minecraft:test has the following contents: {}").unwrap();
+        assert_eq!((data.target.as_str(), data.kind, data.value.as_str()), ("Storage minecraft:test", DataKind::Storage, "{}"));
+        assert_eq!(parse_data_get("No entity was found"), None);
+    }
+}
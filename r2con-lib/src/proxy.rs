@@ -0,0 +1,134 @@
+use std::{error::{self, Error}, fmt, net::IpAddr};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
+
+const SOCKS_VERSION: u8 = 5;
+const AUTH_NONE: u8 = 0;
+const AUTH_USER_PASS: u8 = 2;
+const AUTH_NO_ACCEPTABLE: u8 = 0xFF;
+const CMD_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+#[derive(Debug, Clone)]
+pub struct ProxyError{
+    message:String
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "socks5 proxy error: {}", self.message)
+    }
+}
+
+impl error::Error for ProxyError {}
+
+impl ProxyError {
+    pub(crate) fn new(message:&str) -> ProxyError {
+        return ProxyError { message: message.to_string() };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Socks5Config {
+    addr: String,
+    credentials: Option<(String, String)>
+}
+
+impl Socks5Config {
+    pub fn new(addr:&str) -> Socks5Config {
+        return Socks5Config {
+            addr: addr.to_string(),
+            credentials: None
+        };
+    }
+
+    pub fn credentials(mut self, username:&str, password:&str) -> Socks5Config {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        return self;
+    }
+
+    pub fn get_address(&self) -> &str {
+        return &self.addr;
+    }
+
+    pub(crate) async fn connect(&self, host:&str, port:u16) -> Result<TcpStream, Box<dyn Error + Send + Sync>> {
+        let mut stream = TcpStream::connect(&self.addr).await?;
+        self.authenticate(&mut stream).await?;
+
+        let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(ATYP_IPV4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(ATYP_IPV6);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                let host_len = u8::try_from(host.len())
+                    .map_err(|_| ProxyError::new("hostname too long"))?;
+                request.push(ATYP_DOMAIN);
+                request.push(host_len);
+                request.extend_from_slice(host.as_bytes());
+            }
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(ProxyError::new("invalid reply version").into());
+        }
+        if reply[1] != 0 {
+            return Err(ProxyError::new(&format!("connect failed with reply code {}", reply[1])).into());
+        }
+        // skip the bound address
+        let bound_len = match reply[3] {
+            ATYP_IPV4 => 4,
+            ATYP_IPV6 => 16,
+            ATYP_DOMAIN => usize::from(stream.read_u8().await?),
+            _ => {
+                return Err(ProxyError::new("invalid bound address type").into());
+            }
+        };
+        let mut bound = vec![0u8; bound_len+2];
+        stream.read_exact(&mut bound).await?;
+        return Ok(stream);
+    }
+
+    async fn authenticate(&self, stream:&mut TcpStream) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let method = if self.credentials.is_some() {
+            AUTH_USER_PASS
+        } else {
+            AUTH_NONE
+        };
+        stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+        let mut reply = [0u8; 2];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != SOCKS_VERSION {
+            return Err(ProxyError::new("invalid greeting version").into());
+        }
+        if reply[1] == AUTH_NO_ACCEPTABLE || reply[1] != method {
+            return Err(ProxyError::new("no acceptable authentication method").into());
+        }
+        if let Some((username, password)) = &self.credentials {
+            let username_len = u8::try_from(username.len())
+                .map_err(|_| ProxyError::new("username too long"))?;
+            let password_len = u8::try_from(password.len())
+                .map_err(|_| ProxyError::new("password too long"))?;
+            let mut request = vec![1, username_len];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password_len);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(ProxyError::new("authentication rejected").into());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::{error::{self, Error}, fmt, net::SocketAddr, time::Duration};
+use tokio::{io, net::{lookup_host, ToSocketAddrs, UdpSocket}, time::timeout};
+
+const SINGLE_PACKET: i32 = -1;
+const SPLIT_PACKET: i32 = -2;
+const A2S_INFO: u8 = 0x54;
+const A2S_PLAYER: u8 = 0x55;
+const A2S_RULES: u8 = 0x56;
+const S2A_INFO: u8 = 0x49;
+const S2A_PLAYER: u8 = 0x44;
+const S2A_RULES: u8 = 0x45;
+const S2C_CHALLENGE: u8 = 0x41;
+const INFO_PAYLOAD: &[u8] = b"Source Engine Query\0";
+const NO_CHALLENGE: [u8; 4] = [0xFF; 4];
+const MAX_DATAGRAM_SIZE: usize = 65507;
+const MAX_ATTEMPTS: usize = 3;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone)]
+pub struct QueryError{
+    message:String
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "query error: {}", self.message)
+    }
+}
+
+impl error::Error for QueryError {}
+
+impl QueryError {
+    fn new(message:&str) -> QueryError {
+        return QueryError { message: message.to_string() };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct A2sInfo {
+    pub protocol: u8,
+    pub name: String,
+    pub map: String,
+    pub folder: String,
+    pub game: String,
+    pub app_id: i16,
+    pub players: u8,
+    pub max_players: u8,
+    pub bots: u8,
+    pub server_type: char,
+    pub environment: char,
+    pub visibility: bool,
+    pub vac: bool,
+    pub version: String
+}
+
+#[derive(Debug, Clone)]
+pub struct A2sPlayer {
+    pub name: String,
+    pub score: i32,
+    pub duration: Duration
+}
+
+#[derive(Debug, Clone)]
+pub struct A2sRule {
+    pub name: String,
+    pub value: String
+}
+
+struct Reader<'a> {
+    buf: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n:usize) -> Result<&'a [u8], QueryError> {
+        if self.buf.len() < n {
+            return Err(QueryError::new("truncated response"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, QueryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, QueryError> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, QueryError> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, QueryError> {
+        let bytes = self.take(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, QueryError> {
+        match self.buf.iter().position(|b| *b == 0) {
+            Some(end) => {
+                let bytes = self.take(end+1)?;
+                Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+            }
+            None => Err(QueryError::new("unterminated string"))
+        }
+    }
+}
+
+pub struct A2sClient {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    timeout: Duration
+}
+
+impl A2sClient {
+    pub async fn connect<A: ToSocketAddrs>(addr:A) -> Result<A2sClient, Box<dyn Error + Send + Sync>> {
+        let addr = lookup_host(addr).await?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not resolve address"))?;
+        let bind_addr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        socket.connect(addr).await?;
+        return Ok(A2sClient {
+            socket: socket,
+            addr: addr,
+            timeout: DEFAULT_TIMEOUT
+        });
+    }
+
+    pub fn set_timeout(&mut self, timeout:Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn get_address(&self) -> io::Result<SocketAddr> {
+        return Ok(self.addr);
+    }
+
+    pub async fn info(&self) -> Result<A2sInfo, Box<dyn Error + Send + Sync>> {
+        let response = self.request(A2S_INFO, INFO_PAYLOAD, &[]).await?;
+        let mut reader = Reader { buf: &response };
+        if reader.u8()? != S2A_INFO {
+            return Err(QueryError::new("unexpected response to A2S_INFO").into());
+        }
+        return Ok(A2sInfo {
+            protocol: reader.u8()?,
+            name: reader.string()?,
+            map: reader.string()?,
+            folder: reader.string()?,
+            game: reader.string()?,
+            app_id: reader.i16()?,
+            players: reader.u8()?,
+            max_players: reader.u8()?,
+            bots: reader.u8()?,
+            server_type: char::from(reader.u8()?),
+            environment: char::from(reader.u8()?),
+            visibility: reader.u8()? == 1,
+            vac: reader.u8()? == 1,
+            version: reader.string()?
+        });
+    }
+
+    pub async fn players(&self) -> Result<Vec<A2sPlayer>, Box<dyn Error + Send + Sync>> {
+        let response = self.request(A2S_PLAYER, &[], &NO_CHALLENGE).await?;
+        let mut reader = Reader { buf: &response };
+        if reader.u8()? != S2A_PLAYER {
+            return Err(QueryError::new("unexpected response to A2S_PLAYER").into());
+        }
+        let count = reader.u8()?;
+        let mut players = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            reader.u8()?; // index, always 0 on most servers
+            let name = reader.string()?;
+            let score = reader.i32()?;
+            let duration = reader.f32()?;
+            players.push(A2sPlayer {
+                name: name,
+                score: score,
+                duration: Duration::try_from_secs_f32(duration).unwrap_or_default()
+            });
+        }
+        return Ok(players);
+    }
+
+    pub async fn rules(&self) -> Result<Vec<A2sRule>, Box<dyn Error + Send + Sync>> {
+        let response = self.request(A2S_RULES, &[], &NO_CHALLENGE).await?;
+        let mut reader = Reader { buf: &response };
+        if reader.u8()? != S2A_RULES {
+            return Err(QueryError::new("unexpected response to A2S_RULES").into());
+        }
+        let count = reader.i16()?;
+        let mut rules = Vec::with_capacity(usize::try_from(count).unwrap_or(0));
+        for _ in 0..count {
+            rules.push(A2sRule {
+                name: reader.string()?,
+                value: reader.string()?
+            });
+        }
+        return Ok(rules);
+    }
+
+    async fn request(&self, header:u8, payload:&[u8], challenge:&[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut challenge = challenge.to_vec();
+        for _ in 0..MAX_ATTEMPTS {
+            let mut request = Vec::with_capacity(payload.len()+9);
+            request.extend_from_slice(&SINGLE_PACKET.to_le_bytes());
+            request.push(header);
+            request.extend_from_slice(payload);
+            request.extend_from_slice(&challenge);
+            self.socket.send(&request).await?;
+
+            let response = match timeout(self.timeout, self.receive()).await {
+                Ok(response) => response?,
+                Err(_) => {continue;}
+            };
+            if response.len() >= 5 && response[0] == S2C_CHALLENGE {
+                challenge = response[1..5].to_vec();
+                continue;
+            }
+            return Ok(response);
+        }
+        Err(QueryError::new("no response from server").into())
+    }
+
+    async fn receive(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut parts: Vec<Option<Vec<u8>>> = Vec::new();
+        let mut split_id = None;
+        loop {
+            let n = self.socket.recv(&mut buf).await?;
+            let mut reader = Reader { buf: &buf[..n] };
+            match reader.i32()? {
+                SINGLE_PACKET => {
+                    return Ok(reader.buf.to_vec());
+                }
+                SPLIT_PACKET => {
+                    let id = reader.i32()?;
+                    if id < 0 {
+                        return Err(QueryError::new("compressed responses are not supported").into());
+                    }
+                    let total = usize::from(reader.u8()?);
+                    let number = usize::from(reader.u8()?);
+                    reader.i16()?; // maximum packet size
+                    if split_id != Some(id) {
+                        split_id = Some(id);
+                        parts = vec![None; total];
+                    }
+                    if number < parts.len() {
+                        parts[number] = Some(reader.buf.to_vec());
+                    }
+                    if parts.iter().all(|part| part.is_some()) {
+                        let response = parts.into_iter().flatten().flatten().collect::<Vec<u8>>();
+                        let mut reader = Reader { buf: &response };
+                        if reader.i32()? != SINGLE_PACKET {
+                            return Err(QueryError::new("malformed split response").into());
+                        }
+                        return Ok(reader.buf.to_vec());
+                    }
+                }
+                _ => {
+                    return Err(QueryError::new("malformed packet header").into());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{error::Error, net::SocketAddr};
+use tokio::{sync::{mpsc, oneshot}, task::JoinHandle};
+
+use crate::rcon::{ConnectionClosedError, RconClient, RconError};
+
+pub type ResponseReceiver = oneshot::Receiver<Result<String, RconError>>;
+
+struct QueuedCommand {
+    command: String,
+    reply: oneshot::Sender<Result<String, RconError>>
+}
+
+// a single task owns the client and runs queued commands one at a time,
+// so callers never interleave writes on the socket
+#[derive(Clone)]
+pub struct CommandQueue {
+    tx: mpsc::Sender<QueuedCommand>,
+    addr: SocketAddr
+}
+
+impl CommandQueue {
+    // the task hands the client back once every queue handle is dropped
+    pub(crate) fn spawn(client:RconClient, capacity:usize) -> (CommandQueue, JoinHandle<RconClient>) {
+        let addr = client.get_address().expect("client address is always known");
+        // tokio panics on a zero capacity channel
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let task = tokio::spawn(run(client, rx));
+        return (CommandQueue { tx: tx, addr: addr }, task);
+    }
+
+    // waits for a free slot when the queue is full
+    pub async fn enqueue(&self, command:&str) -> Result<ResponseReceiver, RconError> {
+        let (reply, rx) = oneshot::channel();
+        let queued = QueuedCommand { command: command.to_string(), reply: reply };
+        if self.tx.send(queued).await.is_err() {
+            return Err(self.closed());
+        }
+        Ok(rx)
+    }
+
+    // fails instead of waiting when the queue is full, the command is given back in the error
+    pub fn try_enqueue(&self, command:&str) -> Result<ResponseReceiver, mpsc::error::TrySendError<String>> {
+        let (reply, rx) = oneshot::channel();
+        let queued = QueuedCommand { command: command.to_string(), reply: reply };
+        match self.tx.try_send(queued) {
+            Ok(()) => Ok(rx),
+            Err(mpsc::error::TrySendError::Full(queued)) => Err(mpsc::error::TrySendError::Full(queued.command)),
+            Err(mpsc::error::TrySendError::Closed(queued)) => Err(mpsc::error::TrySendError::Closed(queued.command))
+        }
+    }
+
+    pub async fn send_command(&self, command:&str) -> Result<String, RconError> {
+        let rx = self.enqueue(command).await?;
+        match rx.await {
+            Ok(result) => result,
+            Err(_) => Err(self.closed())
+        }
+    }
+
+    // free slots left before enqueue starts waiting
+    pub fn capacity(&self) -> usize {
+        return self.tx.capacity();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        return self.tx.is_closed();
+    }
+
+    fn closed(&self) -> RconError {
+        let closed: Box<dyn Error + Send + Sync> = ConnectionClosedError{addr:self.addr}.into();
+        return closed.into();
+    }
+}
+
+async fn run(mut client:RconClient, mut rx:mpsc::Receiver<QueuedCommand>) -> RconClient {
+    while let Some(queued) = rx.recv().await {
+        // a caller that dropped its receiver no longer wants the response
+        if queued.reply.is_closed() {
+            continue;
+        }
+        let result = client.send_command(&queued.command).await.map_err(RconError::from);
+        let _ = queued.reply.send(result);
+    }
+    return client;
+}
@@ -0,0 +1,42 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::runtime::{sleep, Instant};
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant
+}
+
+// token bucket allowing bursts of up to one second worth of commands
+pub(crate) struct RateLimiter {
+    rate: f64,
+    bucket: Mutex<Bucket>
+}
+
+impl RateLimiter {
+    pub(crate) fn new(commands_per_second:u32) -> RateLimiter {
+        let rate = f64::from(commands_per_second);
+        return RateLimiter {
+            rate: rate,
+            bucket: Mutex::new(Bucket { tokens: rate, updated: Instant::now() })
+        };
+    }
+
+    pub(crate) async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens = (bucket.tokens+elapsed*self.rate).min(self.rate);
+                bucket.updated = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0-bucket.tokens)/self.rate)
+            };
+            sleep(wait).await;
+        }
+    }
+}
@@ -1,14 +1,29 @@
 
 
 use std::{error::{self, Error}, fmt::{self}, net::SocketAddr, time::Duration};
-use bytes::BytesMut;
-use tokio::{io::{self, AsyncWriteExt}, net::{TcpStream, ToSocketAddrs}, time::sleep};
+use bytes::Bytes;
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use tokio::{io::{self, AsyncWriteExt}, sync::broadcast};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use tokio::{runtime::Handle, task::JoinHandle};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use tokio::net::{TcpStream, ToSocketAddrs};
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use crate::happy_eyeballs;
+use tokio_util::codec::Framed;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+use crate::{queue::CommandQueue, split::CommandSender};
+use std::{collections::{HashMap, HashSet, VecDeque}, sync::Arc};
+#[cfg(feature = "futures-io")]
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+#[cfg(feature = "tls")]
+use tokio_rustls::{rustls::{pki_types::ServerName, ClientConfig}, TlsConnector};
 
-use crate::rcon_packet::{Packet, PacketType};
+use crate::{codec::RconCodec, config::{RconConfig, Termination}, dialect::Dialect, encoding::Encoding, formatting::ColorMode, events::{emit, packet_stream, ConnectionEvent, EVENT_CAPACITY, UNSOLICITED_CAPACITY}, ids::{IdSource, SequentialIds, RESERVED_ID}, rate_limit::RateLimiter, retry::is_retryable, rcon_packet::{Packet, PacketType, ProtocolError}, response::CommandResponse, runtime::{sleep, timeout, Instant}, stats::{ClientStats, StatsRecorder}, transport::Transport};
 
 #[derive(Debug, Clone)]
 pub struct RconAuthError{
-    addr:SocketAddr
+    pub(crate) addr:SocketAddr
 }
 
 impl fmt::Display for RconAuthError {
@@ -21,7 +36,7 @@ impl error::Error for ConnectionClosedError{}
 
 #[derive(Debug, Clone)]
 pub struct ConnectionClosedError{
-    addr:SocketAddr
+    pub(crate) addr:SocketAddr
 }
 
 impl fmt::Display for ConnectionClosedError {
@@ -32,20 +47,136 @@ impl fmt::Display for ConnectionClosedError {
 
 impl error::Error for RconAuthError {}
 
+#[derive(Debug, Clone)]
+pub struct PayloadTooLargeError{
+    pub(crate) size:usize,
+    pub(crate) max:usize
+}
+
+impl fmt::Display for PayloadTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "payload of {} bytes exceeds the maximum of {} bytes", self.size, self.max)
+    }
+}
+
+impl error::Error for PayloadTooLargeError {}
+
+#[derive(Debug, Clone)]
+pub enum RconError{
+    Timeout(Duration),
+    Protocol(ProtocolError),
+    ResponseTooLarge(usize),
+    CannotReconnect(&'static str),
+    // any other failure, e.g. io errors, when a single error type is needed
+    Other(Arc<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for RconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RconError::Timeout(duration) => write!(f, "command timed out after {}ms", duration.as_millis()),
+            RconError::Protocol(e) => write!(f, "protocol error: {}", e),
+            RconError::ResponseTooLarge(max) => write!(f, "response exceeded the maximum of {} bytes", max),
+            RconError::CannotReconnect(reason) => write!(f, "cannot reconnect: {}", reason),
+            RconError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for RconError {}
+
+impl From<Box<dyn Error + Send + Sync>> for RconError {
+    fn from(e: Box<dyn Error + Send + Sync>) -> RconError {
+        match e.downcast::<RconError>() {
+            Ok(e) => *e,
+            Err(e) => RconError::Other(Arc::from(e))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchError{
+    message:String
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "batch failed: {}", self.message)
+    }
+}
+
+impl error::Error for BatchError {}
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+// unmatched packets kept until take_unmatched is called, the oldest are dropped first
+const UNMATCHED_CAPACITY: usize = 64;
+
+// response that was still being read when a command future got dropped
+struct PendingResponse{
+    terminator:Option<i32>,
+}
+
+// request id and packet count of the last response read by send
+#[derive(Default)]
+struct ResponseInfo{
+    request_id:i32,
+    packets:usize,
+}
+
+// progress of a response read by send_command_stream
+struct StreamState{
+    request_id:i32,
+    terminator:Option<i32>,
+    received:bool,
+}
+
+// how the client reached the server, replayed by reconnect()
+#[derive(Clone)]
+pub(crate) enum Endpoint{
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    Tcp(SocketAddr),
+    // the target address as given, resolved by the proxy
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    Proxy(String),
+    #[cfg(feature = "tls")]
+    Tls(SocketAddr, String, Arc<ClientConfig>),
+    Custom,
+}
+
+#[cfg(feature = "secrecy")]
+type StoredPassword = zeroize::Zeroizing<String>;
+#[cfg(not(feature = "secrecy"))]
+type StoredPassword = String;
+
 pub struct RconClient{
-    stream:TcpStream,
+    // only taken by close, split or drop
+    framed:Option<Framed<Box<dyn Transport>, RconCodec>>,
+    addr:SocketAddr,
+    ids:Arc<dyn IdSource>,
+    pending:Option<PendingResponse>,
+    write_interrupted:bool,
+    config:RconConfig,
+    endpoint:Endpoint,
+    password:Option<StoredPassword>,
+    stats:Arc<StatsRecorder>,
+    limiter:Option<Arc<RateLimiter>>,
+    events:broadcast::Sender<ConnectionEvent>,
+    last_sent:Instant,
+    last_response:ResponseInfo,
+    unmatched:VecDeque<Packet>,
+    unsolicited:broadcast::Sender<Packet>,
 }
 
 impl RconClient {
-    pub async fn connect<A: ToSocketAddrs>(addr:A, password:&str) -> Result<RconClient, Box<dyn Error>> {
-        let stream = TcpStream::connect(addr).await;
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn connect<A: ToSocketAddrs>(addr:A, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let stream = happy_eyeballs::connect(addr).await;
         match stream {
             Ok(s) => {
-                let mut client = RconClient{
-                    stream:s,
-                };
-                client.login(password).await?;
-                return Ok(client);
+                let peer_addr = s.peer_addr()?;
+                return RconClient::connect_with_config(s, peer_addr, password, RconConfig::default(), Endpoint::Tcp(peer_addr)).await;
             }
             Err(e)=>{
                 return Err(e.into());
@@ -53,100 +184,789 @@ impl RconClient {
         }
     }
 
+    #[cfg(feature = "tls")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(domain = domain)))]
+    pub async fn connect_tls<A: ToSocketAddrs>(addr:A, domain:&str, password:&str, tls_config:Arc<ClientConfig>) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let server_name = ServerName::try_from(domain.to_string())?;
+        let stream = happy_eyeballs::connect(addr).await?;
+        let peer_addr = stream.peer_addr()?;
+        let connector = TlsConnector::from(tls_config.clone());
+        let tls_stream = connector.connect(server_name, stream).await?;
+        let endpoint = Endpoint::Tls(peer_addr, domain.to_string(), tls_config);
+        return RconClient::connect_with_config(tls_stream, peer_addr, password, RconConfig::default(), endpoint).await;
+    }
+
+    pub async fn connect_with_transport<T: Transport + 'static>(transport:T, addr:SocketAddr, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        return RconClient::connect_with_config(transport, addr, password, RconConfig::default(), Endpoint::Custom).await;
+    }
+
+    // lets runtimes other than tokio provide the transport, e.g. an async-std or smol TcpStream
+    #[cfg(feature = "futures-io")]
+    pub async fn connect_with_futures_io<T>(transport:T, addr:SocketAddr, password:&str) -> Result<RconClient, Box<dyn Error + Send + Sync>>
+    where T: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Unpin + Send + 'static {
+        return RconClient::connect_with_transport(transport.compat(), addr, password).await;
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(addr = %addr)))]
+    pub(crate) async fn connect_with_config<T: Transport + 'static>(transport:T, addr:SocketAddr, password:&str, config:RconConfig, endpoint:Endpoint) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+        let stored_password = match config.store_credentials {
+            true => Some(StoredPassword::from(password.to_string())),
+            false => None
+        };
+        let limiter = config.max_commands_per_second.map(|rate| Arc::new(RateLimiter::new(rate)));
+        let events = config.events.clone().unwrap_or_else(|| broadcast::channel(EVENT_CAPACITY).0);
+        emit(&events, ConnectionEvent::Connected);
+        let mut client = RconClient{
+            framed:Some(Framed::new(Box::new(transport) as Box<dyn Transport>, RconCodec::new())),
+            addr:addr,
+            pending:None,
+            write_interrupted:false,
+            ids:config.id_source.clone().unwrap_or_else(|| Arc::new(SequentialIds::new()) as Arc<dyn IdSource>),
+            config:config,
+            endpoint:endpoint,
+            password:stored_password,
+            stats:Arc::new(StatsRecorder::default()),
+            limiter:limiter,
+            events:events,
+            last_sent:Instant::now(),
+            last_response:ResponseInfo::default(),
+            unmatched:VecDeque::new(),
+            unsolicited:broadcast::channel(UNSOLICITED_CAPACITY).0,
+        };
+        client.login(password).await?;
+        return Ok(client);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionEvent> {
+        return self.events.subscribe();
+    }
+
+    // packets that matched no outstanding request, e.g. chat relays or log lines pushed by the server.
+    // they only arrive while the client is reading, so between commands nothing is delivered
+    pub fn subscribe_unsolicited(&self) -> impl Stream<Item = Packet> {
+        return packet_stream(self.unsolicited.subscribe());
+    }
+
+    pub async fn reconnect(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.reconnect_attempt(1).await
+    }
+
+    pub async fn reconnect_with_password(&mut self, password:&str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.redial(password, 1).await
+    }
+
+    async fn reconnect_attempt(&mut self, attempt:u32) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let password = match &self.password {
+            Some(password) => password.clone(),
+            None => return Err(RconError::CannotReconnect("credentials were not stored").into())
+        };
+        self.redial(&password, attempt).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(addr = %self.addr, attempt = attempt)))]
+    async fn redial(&mut self, password:&str, attempt:u32) -> Result<(), Box<dyn Error + Send + Sync>> {
+        emit(&self.events, ConnectionEvent::Reconnecting(attempt));
+        let transport: Box<dyn Transport> = match self.endpoint.clone() {
+            #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect(addr).await?;
+                stream.set_nodelay(self.config.tcp_nodelay)?;
+                Box::new(stream)
+            }
+            #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+            Endpoint::Proxy(target) => Box::new(self.config.dial(&target).await?.0),
+            #[cfg(feature = "tls")]
+            Endpoint::Tls(addr, domain, tls_config) => {
+                let server_name = ServerName::try_from(domain)?;
+                let stream = TcpStream::connect(addr).await?;
+                stream.set_nodelay(self.config.tcp_nodelay)?;
+                Box::new(TlsConnector::from(tls_config).connect(server_name, stream).await?)
+            }
+            Endpoint::Custom => return Err(RconError::CannotReconnect("the client was created from a custom transport").into())
+        };
+        self.shutdown().await;
+        emit(&self.events, ConnectionEvent::Connected);
+        self.framed = Some(Framed::new(transport, RconCodec::new()));
+        self.pending = None;
+        self.write_interrupted = false;
+        self.login(password).await?;
+        self.stats.record_reconnect();
+        Ok(())
+    }
+
     pub fn get_address(&self) -> io::Result<SocketAddr>{
-        return self.stream.peer_addr();
+        return Ok(self.addr);
+    }
+
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    pub fn split(mut self) -> (CommandSender, JoinHandle<()>) {
+        let framed = self.framed.take().expect("transport is only taken once");
+        return CommandSender::spawn(framed, self.addr, self.ids.clone(), self.config.clone(), self.stats.clone(), self.limiter.clone(), self.events.clone(), self.unsolicited.clone());
+    }
+
+    // commands sent through the queue run one at a time, enqueue waits once capacity commands are pending
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    pub fn queue(self, capacity:usize) -> (CommandQueue, JoinHandle<RconClient>) {
+        return CommandQueue::spawn(self, capacity);
+    }
+
+    pub async fn close(mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // finish reading any response left behind by a dropped command
+        let drained = match timeout(CLOSE_TIMEOUT, self.recover()).await {
+            Ok(result) => result,
+            Err(_) => Err(RconError::Timeout(CLOSE_TIMEOUT).into())
+        };
+        let mut framed = self.framed.take().expect("transport is only taken once");
+        framed.get_mut().shutdown().await?;
+        // wait for the server to close its side of the connection
+        let _ = timeout(CLOSE_TIMEOUT, async {
+            while let Some(Ok(_)) = framed.next().await {}
+        }).await;
+        emit(&self.events, ConnectionEvent::Disconnected("closed by client".to_string()));
+        drained
+    }
+
+    // packets read while waiting for a response that carried another request's id
+    pub fn take_unmatched(&mut self) -> Vec<Packet> {
+        return self.unmatched.drain(..).collect();
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        return self.stats.snapshot();
+    }
+
+    pub fn get_dialect(&self) -> &Dialect {
+        return &self.config.dialect;
+    }
+
+    pub fn set_encoding(&mut self, encoding:Encoding) {
+        self.config.encoding = encoding;
+    }
+
+    pub fn set_color_mode(&mut self, mode:ColorMode) {
+        self.config.color_mode = mode;
+    }
+
+    pub fn set_packet_delay(&mut self, delay:Duration) {
+        self.config.packet_delay = delay;
+    }
+
+    pub fn set_max_response_size(&mut self, max:Option<usize>) {
+        self.config.max_response_size = max;
+    }
+
+    pub fn set_command_timeout(&mut self, timeout:Option<Duration>) {
+        self.config.command_timeout = timeout;
     }
 
-    pub async fn send_command(&mut self, command: &str) -> Result<String, Box<dyn Error >> {
+    pub async fn send_command(&mut self, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let output = self.send_command_raw(command).await?;
+        self.config.decode(&output)
+    }
+
+    pub async fn send_command_detailed(&mut self, command: &str) -> Result<CommandResponse, Box<dyn Error + Send + Sync>> {
+        let start = Instant::now();
+        let output = self.send_command_raw(command).await?;
+        let body = self.config.decode(&output)?;
+        return Ok(CommandResponse {
+            command: command.to_string(),
+            body: body,
+            request_id: self.last_response.request_id,
+            packets: self.last_response.packets,
+            round_trip: start.elapsed()
+        });
+    }
+
+    pub async fn send_command_raw(&mut self, command: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let deadline = self.config.command_timeout;
+        self.send_command_with_deadline(command, deadline).await
+    }
+
+    pub async fn send_command_with_timeout(&mut self, command: &str, deadline: Duration) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let output = self.send_command_with_deadline(command, Some(deadline)).await?;
+        self.config.decode(&output)
+    }
+
+    // a timed out command is dropped mid-flight, recover() cleans up before the next one
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "send_command", skip_all, fields(addr = %self.addr, command = command)))]
+    async fn send_command_with_deadline(&mut self, command: &str, deadline: Option<Duration>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut result = self.send_command_once(command, deadline).await;
+        let mut attempt = 1;
+        // a command that timed out may already have run, retrying can run it twice
+        while let Some(policy) = self.config.retry_policy {
+            match &result {
+                Err(e) if attempt < policy.attempts && is_retryable(e.as_ref()) => {}
+                _ => break
+            }
+            sleep(policy.delay(attempt)).await;
+            attempt += 1;
+            result = match self.reconnect_attempt(attempt-1).await {
+                Ok(_) => self.send_command_once(command, deadline).await,
+                Err(e) => Err(e)
+            };
+        }
+        result
+    }
+
+    async fn send_command_once(&mut self, command: &str, deadline: Option<Duration>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        self.stats.record_commands(1);
+        let start = Instant::now();
+        let result = match deadline {
+            Some(deadline) => match timeout(deadline, self.send_command_bytes(command)).await {
+                Ok(result) => result,
+                Err(_) => Err(RconError::Timeout(deadline).into())
+            },
+            None => self.send_command_bytes(command).await
+        };
+        if result.is_ok() {
+            self.stats.record_latency(start.elapsed());
+        }
+        result.map(|output| self.strip_empty_response(output))
+    }
+
+    fn strip_empty_response(&self, output:Vec<u8>) -> Vec<u8> {
+        match &self.config.empty_response {
+            Some(placeholder) if output == placeholder.as_bytes() => Vec::new(),
+            _ => output
+        }
+    }
+
+    async fn send_command_bytes(&mut self, command: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let max = self.config.max_payload_size;
+        if command.len() > max {
+            return Err(PayloadTooLargeError{size:command.len(), max:max}.into());
+        }
+        self.refresh_if_idle().await?;
+        self.throttle().await;
         match self.send(PacketType::Command, command).await {
             Ok(result) => Ok(result),
             Err(e) => {
-                let _ = self.stream.shutdown().await;
+                self.disconnect(e.as_ref()).await;
                 Err(e)
             }
         }
     }
- 
-    async fn login(&mut self, password:&str) -> Result<(), Box<dyn Error>> {
-        match self.send(PacketType::Login, password).await {
+
+    // yields the body of every response packet as it arrives instead of buffering the whole response
+    pub fn send_command_stream<'a>(&'a mut self, command: &'a str) -> impl Stream<Item = Result<Bytes, Box<dyn Error + Send + Sync>>> + 'a {
+        stream::unfold((self, None::<StreamState>, false), move |(client, state, done)| async move {
+            if done {
+                return None;
+            }
+            let mut state = match state {
+                Some(state) => state,
+                None => match client.start_stream(command).await {
+                    Ok(state) => state,
+                    Err(e) => {
+                        if !e.is::<PayloadTooLargeError>() {
+                            client.disconnect(e.as_ref()).await;
+                        }
+                        return Some((Err(e), (client, None, true)));
+                    }
+                }
+            };
+            match client.next_fragment(&mut state).await {
+                Ok(Some(bytes)) => Some((Ok(bytes), (client, Some(state), false))),
+                Ok(None) => None,
+                Err(e) => {
+                    client.disconnect(e.as_ref()).await;
+                    Some((Err(e), (client, Some(state), true)))
+                }
+            }
+        })
+    }
+
+    async fn start_stream(&mut self, command: &str) -> Result<StreamState, Box<dyn Error + Send + Sync>> {
+        let max = self.config.max_payload_size;
+        if command.len() > max {
+            return Err(PayloadTooLargeError{size:command.len(), max:max}.into());
+        }
+        self.stats.record_commands(1);
+        self.throttle().await;
+        self.recover().await?;
+        let packet = Packet::with_id(self.next_id(), PacketType::Command, command)?;
+        if let Termination::DummyPacket = self.config.termination {
+            self.pending = Some(PendingResponse{terminator:None});
+        }
+        self.send_packet(&packet).await?;
+        let terminator = match self.config.termination {
+            Termination::DummyPacket => {
+                let dummy_packet = Packet::with_id(self.next_id(), PacketType::Response, "")?;
+                self.pace().await;
+                self.send_packet(&dummy_packet).await?;
+                self.pending = Some(PendingResponse{terminator:Some(*dummy_packet.get_id())});
+                Some(*dummy_packet.get_id())
+            }
+            _ => None
+        };
+        Ok(StreamState{request_id:*packet.get_id(), terminator:terminator, received:false})
+    }
+
+    async fn next_fragment(&mut self, state: &mut StreamState) -> Result<Option<Bytes>, Box<dyn Error + Send + Sync>> {
+        loop {
+            let packet = match (self.config.termination, state.received) {
+                (Termination::SinglePacket, true) => return Ok(None),
+                (Termination::Timeout(idle), true) => match timeout(idle, self.read_packet()).await {
+                    Ok(packet) => packet?,
+                    Err(_) => return Ok(None)
+                },
+                _ => self.read_packet().await?
+            };
+            if *packet.get_id() == RESERVED_ID {
+                return Err(RconAuthError {addr:self.addr}.into());
+            }
+            if Some(*packet.get_id()) == state.terminator {
+                self.pending = None;
+                return Ok(None);
+            }
+            if *packet.get_id() != state.request_id {
+                self.stash(packet);
+                continue;
+            }
+            if let PacketType::Response = packet.get_p_type() {
+                state.received = true;
+                return Ok(Some(Bytes::from(packet.into_body())));
+            }
+        }
+    }
+
+    pub async fn health_check(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let result = match self.config.termination {
+            // an unanswered terminator packet is a no-op for the server
+            Termination::DummyPacket => match self.recover().await {
+                Ok(_) => self.read_until_dummy(None).await,
+                Err(e) => Err(e)
+            },
+            _ => self.send(PacketType::Command, "").await
+        };
+        match result {
             Ok(_) => Ok(()),
             Err(e) => {
-                let _ = self.stream.shutdown().await;
+                self.disconnect(e.as_ref()).await;
                 Err(e)
             }
         }
     }
 
-    async fn send_packet(&mut self, packet:&Packet) -> Result<(), Box<dyn Error>> {
-        let bytes:Vec<u8> = packet.into();
-        let bytes_len = bytes.len();
-        let mut bytes_written = 0;
-        loop {
-            self.stream.writable().await?;
-            match self.stream.try_write(&bytes[bytes_written..]) {
-                Ok(0) => {
-                    return Err(ConnectionClosedError{addr:self.stream.peer_addr()?}.into());
-                }
-                Ok(n) => {
-                    bytes_written+=n;
-                    if bytes_written == bytes_len {
-                        break;
-                    }
+    // servers like ARK drop connections that stay quiet for too long
+    pub fn needs_keepalive(&self) -> bool {
+        return self.config.keepalive_interval.is_some_and(|interval| self.last_sent.elapsed() >= interval);
+    }
+
+    pub async fn keepalive(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.health_check().await
+    }
+
+    // a connection quiet for longer than the keepalive interval has likely been dropped by the
+    // server, it is checked before the next command and replaced when it is gone
+    async fn refresh_if_idle(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if !self.needs_keepalive() {
+            return Ok(());
+        }
+        match self.keepalive().await {
+            Ok(_) => Ok(()),
+            Err(_) => self.reconnect_attempt(1).await
+        }
+    }
+
+    pub async fn is_alive(&mut self) -> bool {
+        let deadline = self.config.command_timeout.unwrap_or(HEALTH_CHECK_TIMEOUT);
+        match timeout(deadline, self.health_check()).await {
+            Ok(result) => result.is_ok(),
+            Err(_) => false
+        }
+    }
+
+    pub async fn send_commands(&mut self, commands: &[&str]) -> Vec<Result<String, Box<dyn Error + Send + Sync>>> {
+        let max = self.config.max_payload_size;
+        let mut results: Vec<Option<Result<String, Box<dyn Error + Send + Sync>>>> = commands.iter().map(|_| None).collect();
+        let mut pending = Vec::new();
+        for (i, command) in commands.iter().enumerate() {
+            if command.len() > max {
+                results[i] = Some(Err(PayloadTooLargeError{size:command.len(), max:max}.into()));
+            } else {
+                pending.push(i);
+            }
+        }
+        let pending_commands = pending.iter().map(|i| commands[*i]).collect::<Vec<&str>>();
+        self.stats.record_commands(pending_commands.len());
+        let start = Instant::now();
+        match self.send_batch(&pending_commands).await {
+            Ok(outputs) => {
+                self.stats.record_latency(start.elapsed());
+                for (i, output) in pending.iter().zip(outputs) {
+                    results[*i] = Some(self.config.decode(&output));
                 }
-                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                    continue;
+            }
+            Err(e) => {
+                self.disconnect(e.as_ref()).await;
+                let message = e.to_string();
+                for i in pending {
+                    results[i] = Some(Err(BatchError{message:message.clone()}.into()));
                 }
-                Err(e) => {
-                    return Err(e.into());
+            }
+        }
+        results.into_iter().flatten().collect()
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(addr = %self.addr)))]
+    async fn login(&mut self, password:&str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self.send(PacketType::Login, password).await {
+            Ok(_) => {
+                emit(&self.events, ConnectionEvent::Authenticated);
+                Ok(())
+            }
+            Err(e) => {
+                if e.is::<RconAuthError>() {
+                    self.stats.record_auth_failure();
                 }
+                self.disconnect(e.as_ref()).await;
+                Err(e)
             }
         }
+    }
+
+    pub async fn send_packet(&mut self, packet:&Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.stats.record_written(packet);
+        self.last_sent = Instant::now();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(id = *packet.get_id(), packet_type = packet.get_p_type().to_i32(), size = *packet.get_size(), "packet written");
+        #[cfg(feature = "secrecy")]
+        if let PacketType::Login = packet.get_p_type() {
+            return self.send_login_packet(packet).await;
+        }
+        // the codec keeps unflushed bytes buffered, a dropped send is finished by the next flush
+        self.framed().send(packet).await?;
         Ok(())
     }
 
-    async fn send(&mut self, packet_type:PacketType, payload: &str)-> Result<String, Box<dyn Error>>{
-        let mut result_bytes = Vec::<u8>::new();
-        let packet = Packet::new(packet_type, payload)?;
-        let dummy_packet = Packet::new(PacketType::Response, "")?;
+    // buffers the packet without flushing, the next send_packet writes it out
+    async fn feed_packet(&mut self, packet:&Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.stats.record_written(packet);
+        self.last_sent = Instant::now();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(id = *packet.get_id(), packet_type = packet.get_p_type().to_i32(), size = *packet.get_size(), "packet written");
+        self.framed().feed(packet).await?;
+        Ok(())
+    }
 
-        self.send_packet(&packet).await?;
-        // wait before sending a new packet 
-        //(minecraft closes the connection otherwise)
-        sleep(Duration::from_millis(5)).await; 
-        self.send_packet(&dummy_packet).await?;
-        sleep(Duration::from_millis(5)).await;
-
-        let mut packet_data = BytesMut::with_capacity(4096);
-        'outer: loop {
-            self.stream.readable().await?;    
-            match self.stream.try_read_buf(&mut packet_data) {
-                Ok(0) => {
-                    return Err(ConnectionClosedError{addr:self.stream.peer_addr()?}.into());
-                },
-                Ok(_n) => {},
-                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                    continue;
-                }
-                Err(e) => {
-                    return Err(e.into());
+    // bypasses the codec's write buffer so the serialized password can be wiped,
+    // a write dropped halfway leaves a partial frame on the wire that nothing can finish
+    #[cfg(feature = "secrecy")]
+    async fn send_login_packet(&mut self, packet:&Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let bytes = zeroize::Zeroizing::new(Vec::<u8>::from(packet));
+        self.write_interrupted = true;
+        let framed = self.framed();
+        SinkExt::<Packet>::flush(framed).await?;
+        framed.get_mut().write_all(&bytes).await?;
+        framed.get_mut().flush().await?;
+        self.write_interrupted = false;
+        Ok(())
+    }
+
+    pub async fn read_packet(&mut self) -> Result<Packet, Box<dyn Error + Send + Sync>> {
+        match self.framed().next().await {
+            Some(Ok(packet)) => {
+                self.stats.record_read(&packet);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(id = *packet.get_id(), packet_type = packet.get_p_type().to_i32(), size = *packet.get_size(), "packet read");
+                Ok(packet)
+            }
+            Some(Err(e)) => Err(unwrap_io_error(e)),
+            None => Err(ConnectionClosedError{addr:self.addr}.into())
+        }
+    }
+
+    async fn send(&mut self, packet_type:PacketType, payload: &str)-> Result<Vec<u8>, Box<dyn Error + Send + Sync>>{
+        self.recover().await?;
+        let packet = Packet::with_id(self.next_id(), packet_type, payload)?;
+        self.last_response = ResponseInfo{request_id:*packet.get_id(), packets:0};
+
+        let mut coalesce = false;
+        if let Termination::DummyPacket = self.config.termination {
+            self.pending = Some(PendingResponse{terminator:None});
+            coalesce = self.config.packet_delay.is_zero();
+        }
+        if coalesce {
+            // the dummy packet's flush writes both packets at once
+            self.feed_packet(&packet).await?;
+        } else {
+            self.send_packet(&packet).await?;
+        }
+
+        let request_id = *packet.get_id();
+        match self.config.termination {
+            Termination::DummyPacket => self.read_until_dummy(Some(request_id)).await,
+            Termination::SinglePacket => self.read_single(request_id, packet_type).await,
+            Termination::Timeout(idle) => self.read_until_timeout(request_id, packet_type, idle).await
+        }
+    }
+
+    fn framed(&mut self) -> &mut Framed<Box<dyn Transport>, RconCodec> {
+        return self.framed.as_mut().expect("transport is only taken once");
+    }
+
+    async fn shutdown(&mut self) {
+        if let Some(framed) = self.framed.as_mut() {
+            let _ = framed.get_mut().shutdown().await;
+        }
+    }
+
+    // closes the transport after an error that leaves the connection unusable
+    async fn disconnect(&mut self, reason:&(dyn Error + Send + Sync)) {
+        self.shutdown().await;
+        emit(&self.events, ConnectionEvent::Disconnected(reason.to_string()));
+    }
+
+    // discards whatever is left of a response whose command future was dropped
+    async fn recover(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.write_interrupted {
+            self.write_interrupted = false;
+            let e = ConnectionClosedError{addr:self.addr};
+            self.disconnect(&e).await;
+            return Err(e.into());
+        }
+        if let Some(terminator) = self.pending.as_ref().map(|pending| pending.terminator) {
+            let terminator = match terminator {
+                Some(id) => id,
+                None => {
+                    let dummy_packet = Packet::with_id(self.next_id(), PacketType::Response, "")?;
+                    self.send_packet(&dummy_packet).await?;
+                    self.pending = Some(PendingResponse{terminator:Some(*dummy_packet.get_id())});
+                    *dummy_packet.get_id()
                 }
             };
-            while let Some(packet) = Packet::deserialize(&mut packet_data)? {
-                match packet.get_p_type() {
-                    PacketType::Invalid => {continue;} // skip invalid packets
-                    _ => {}
+            loop {
+                let packet = self.read_packet().await?;
+                if *packet.get_id() == terminator {
+                    break;
+                }
+            }
+            self.pending = None;
+        }
+        Ok(())
+    }
+
+    fn next_id(&self) -> i32 {
+        loop {
+            let id = self.ids.next_id();
+            if id != RESERVED_ID {
+                return id;
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(addr = %self.addr, commands = commands.len())))]
+    async fn send_batch(&mut self, commands: &[&str]) -> Result<Vec<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+        self.recover().await?;
+        if let Termination::DummyPacket = self.config.termination {
+            self.pending = Some(PendingResponse{terminator:None});
+        }
+        let mut ids = Vec::with_capacity(commands.len());
+        for (i, command) in commands.iter().enumerate() {
+            if i > 0 {
+                self.pace().await;
+            }
+            self.throttle().await;
+            let packet = Packet::with_id(self.next_id(), PacketType::Command, command)?;
+            self.send_packet(&packet).await?;
+            ids.push(*packet.get_id());
+        }
+        let mut outputs: HashMap<i32, Vec<u8>> = ids.iter().map(|id| (*id, Vec::new())).collect();
+
+        let idle = match self.config.termination {
+            Termination::DummyPacket => {
+                let dummy_packet = Packet::with_id(self.next_id(), PacketType::Response, "")?;
+                self.pace().await;
+                self.send_packet(&dummy_packet).await?;
+                self.pending = Some(PendingResponse{terminator:Some(*dummy_packet.get_id())});
+                loop {
+                    let packet = self.read_packet().await?;
+                    if *packet.get_id() == RESERVED_ID {
+                        return Err(RconAuthError {addr:self.addr}.into());
+                    }
+                    if *packet.get_id() == *dummy_packet.get_id() {
+                        self.pending = None;
+                        break;
+                    }
+                    if !outputs.contains_key(packet.get_id()) {
+                        self.stash(packet);
+                        continue;
+                    }
+                    if let (PacketType::Response, Some(output)) = (packet.get_p_type(), outputs.get_mut(packet.get_id())) {
+                        append_response(output, packet.get_body(), self.config.max_response_size)?;
+                    }
                 }
-                let packet_id = packet.get_id();
-                if *packet_id == -1 {
-                    return Err(RconAuthError {addr:self.stream.peer_addr()?}.into());
+                return Ok(ids.iter().map(|id| outputs.remove(id).unwrap_or_default()).collect());
+            }
+            Termination::SinglePacket => None,
+            Termination::Timeout(idle) => Some(idle)
+        };
+
+        let mut received = HashSet::new();
+        loop {
+            let all_received = received.len() == ids.len();
+            let packet = match (all_received, idle) {
+                (true, None) => break,
+                (true, Some(idle)) => match timeout(idle, self.read_packet()).await {
+                    Ok(packet) => packet?,
+                    Err(_) => break
+                },
+                (false, _) => self.read_packet().await?
+            };
+            if *packet.get_id() == RESERVED_ID {
+                return Err(RconAuthError {addr:self.addr}.into());
+            }
+            if !outputs.contains_key(packet.get_id()) {
+                self.stash(packet);
+                continue;
+            }
+            let single = idle.is_none();
+            if let (PacketType::Response, Some(output)) = (packet.get_p_type(), outputs.get_mut(packet.get_id())) {
+                if !(single && received.contains(packet.get_id())) {
+                    append_response(output, packet.get_body(), self.config.max_response_size)?;
+                    received.insert(*packet.get_id());
                 }
-                if *packet_id == *(dummy_packet.get_id()) {
-                    break 'outer;
-                } 
-                result_bytes.extend_from_slice(packet.get_body());
             }
         }
-        let output = String::from_utf8(result_bytes)?;
-        Ok(output)
+        Ok(ids.iter().map(|id| outputs.remove(id).unwrap_or_default()).collect())
+    }
+
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+    }
+
+    // wait before sending a new packet 
+    //(minecraft closes the connection otherwise)
+    async fn pace(&self) {
+        if !self.config.packet_delay.is_zero() {
+            sleep(self.config.packet_delay).await;
+        }
+    }
+
+    // request_id is None when only the dummy packet was sent, e.g. by health_check
+    async fn read_until_dummy(&mut self, request_id:Option<i32>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut result_bytes = Vec::<u8>::new();
+        let dummy_packet = Packet::with_id(self.next_id(), PacketType::Response, "")?;
+        self.pace().await;
+        self.send_packet(&dummy_packet).await?;
+        self.pending = Some(PendingResponse{terminator:Some(*dummy_packet.get_id())});
+        self.pace().await;
+
+        loop {
+            let packet = self.read_packet().await?;
+            // no request expects these types, they go to the unsolicited packets instead of the response
+            if let PacketType::Invalid | PacketType::Custom(_) = packet.get_p_type() {
+                self.stash(packet);
+                continue;
+            }
+            let packet_id = packet.get_id();
+            if *packet_id == RESERVED_ID {
+                return Err(RconAuthError {addr:self.addr}.into());
+            }
+            if *packet_id == *(dummy_packet.get_id()) {
+                self.pending = None;
+                break;
+            }
+            if Some(*packet_id) != request_id {
+                self.stash(packet);
+                continue;
+            }
+            self.collect(&mut result_bytes, packet.get_body())?;
+        }
+        Ok(result_bytes)
+    }
+
+    // keeps packets that answer no outstanding request out of the response being read
+    fn stash(&mut self, packet:Packet) {
+        // sending only fails when nobody is subscribed
+        let _ = self.unsolicited.send(packet.clone());
+        if self.unmatched.len() == UNMATCHED_CAPACITY {
+            self.unmatched.pop_front();
+        }
+        self.unmatched.push_back(packet);
+    }
+
+    fn collect(&mut self, output:&mut Vec<u8>, body:&[u8]) -> Result<(), RconError> {
+        self.last_response.packets += 1;
+        append_response(output, body, self.config.max_response_size)
+    }
+
+    async fn read_response_packet(&mut self, request_id:i32, request_type:PacketType) -> Result<Packet, Box<dyn Error + Send + Sync>> {
+        loop {
+            let packet = self.read_packet().await?;
+            if *packet.get_id() == RESERVED_ID {
+                return Err(RconAuthError {addr:self.addr}.into());
+            }
+            if *packet.get_id() != request_id {
+                self.stash(packet);
+                continue;
+            }
+            // source servers send an empty response before the auth response
+            match (request_type, packet.get_p_type()) {
+                (PacketType::Login, PacketType::Command) => return Ok(packet),
+                (PacketType::Login, _) => {}
+                (_, PacketType::Response) => return Ok(packet),
+                _ => {}
+            }
+        }
+    }
+
+    async fn read_single(&mut self, request_id:i32, request_type:PacketType) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let packet = self.read_response_packet(request_id, request_type).await?;
+        let mut result_bytes = Vec::new();
+        self.collect(&mut result_bytes, packet.get_body())?;
+        Ok(result_bytes)
+    }
+
+    async fn read_until_timeout(&mut self, request_id:i32, request_type:PacketType, idle:Duration) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let packet = self.read_response_packet(request_id, request_type).await?;
+        let mut result_bytes = Vec::new();
+        self.collect(&mut result_bytes, packet.get_body())?;
+        if let PacketType::Login = request_type {
+            return Ok(result_bytes);
+        }
+        // keep collecting until the server stays quiet for the idle duration
+        while let Ok(packet) = timeout(idle, self.read_response_packet(request_id, request_type)).await {
+            self.collect(&mut result_bytes, packet?.get_body())?;
+        }
+        Ok(result_bytes)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+impl Drop for RconClient {
+    fn drop(&mut self) {
+        // shutdown needs the runtime, without one the socket is simply closed
+        if let Some(mut framed) = self.framed.take() {
+            if let Ok(handle) = Handle::try_current() {
+                handle.spawn(async move {
+                    let _ = framed.get_mut().shutdown().await;
+                });
+            }
+        }
+    }
+}
+
+pub(crate) fn append_response(output:&mut Vec<u8>, body:&[u8], max:Option<usize>) -> Result<(), RconError> {
+    if let Some(max) = max {
+        if output.len()+body.len() > max {
+            return Err(RconError::ResponseTooLarge(max));
+        }
+    }
+    output.extend_from_slice(body);
+    Ok(())
+}
+
+// the codec wraps protocol errors in io::Error, surface them directly
+pub(crate) fn unwrap_io_error(e:io::Error) -> Box<dyn Error + Send + Sync> {
+    if e.get_ref().map_or(false, |inner| inner.is::<RconError>()) {
+        if let Some(inner) = e.into_inner() {
+            return inner;
+        }
+        return io::Error::from(io::ErrorKind::InvalidData).into();
     }
+    e.into()
 }
@@ -1,15 +1,44 @@
-use std::error::Error;
+use std::{error::Error, fmt};
 
 use bytes::{Buf, BufMut, BytesMut};
-use rand::Rng;
+use crate::{ids::{IdSource, SequentialIds}, rcon::RconError};
+
+// ids for packets built without one, clients use their own IdSource
+static PACKET_IDS: SequentialIds = SequentialIds::new();
+
+// largest request body accepted by minecraft
+pub const MAX_PAYLOAD_SIZE: usize = 1446;
+
+// size field of an empty packet: id, type and both terminators
+pub const MIN_PACKET_SIZE: i32 = 10;
+// generous upper bound, responses are usually split around 4096 bytes
+pub const MAX_PACKET_SIZE: i32 = 1024*1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    BadSize(i32),
+    BadTerminator([u8; 2]),
+    TruncatedFrame{expected:usize, received:usize},
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::BadSize(size) => write!(f, "invalid packet size {} (expected {} to {})", size, MIN_PACKET_SIZE, MAX_PACKET_SIZE),
+            ProtocolError::BadTerminator(bytes) => write!(f, "invalid packet terminator {:02x} {:02x} (expected 00 00)", bytes[0], bytes[1]),
+            ProtocolError::TruncatedFrame{expected, received} => write!(f, "connection closed after {} of {} frame bytes", received, expected),
+        }
+    }
+}
 
-#[repr(i32)]
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PacketType {
-    Response = 0i32,
-    Command = 2i32,
-    Login = 3i32,
-    Invalid = -2i32
+    Response,
+    Command,
+    Login,
+    Invalid,
+    Custom(i32)
 }
 
 impl PacketType {
@@ -18,11 +47,24 @@ impl PacketType {
             0 => PacketType::Response,
             2 => PacketType::Command,
             3 => PacketType::Login,
-            _ => PacketType::Invalid
+            -2 => PacketType::Invalid,
+            _ => PacketType::Custom(i)
+        }
+    }
+
+    pub fn to_i32(&self) -> i32{
+        return match self {
+            PacketType::Response => 0,
+            PacketType::Command => 2,
+            PacketType::Login => 3,
+            PacketType::Invalid => -2,
+            PacketType::Custom(i) => *i
         }
     }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Packet {
     size: i32,
     id: i32,
@@ -33,18 +75,22 @@ pub struct Packet {
 
 impl Packet{
 
-    pub fn new(packet_type:PacketType, payload:&str) -> Result<Packet, Box<dyn Error>>{
-        let mut rng = rand::thread_rng();
+    // the id is never RESERVED_ID, use with_id for a specific one
+    pub fn new(packet_type:PacketType, payload:&str) -> Result<Packet, Box<dyn Error + Send + Sync>>{
+        return Packet::with_id(PACKET_IDS.next_id(), packet_type, payload);
+    }
+
+    pub fn with_id(id:i32, packet_type:PacketType, payload:&str) -> Result<Packet, Box<dyn Error + Send + Sync>>{
         let payload_len = payload.len()+1; // add null terminator
         let size = i32::try_from(payload_len+9)?;
         let packet = Packet{
             size: size,
-            id: rng.gen::<i32>(),
+            id: id,
             p_type: packet_type,
             body: payload.as_bytes().to_vec()
         };
         return Ok(packet);
-    } 
+    }
 
     pub fn get_size(&self) -> &i32{
         return &self.size;
@@ -62,65 +108,211 @@ impl Packet{
         return &self.body;
     }
 
-    pub fn deserialize(buf:&mut BytesMut) -> Result<Option<Self>, Box<dyn Error>>{
-        let mut buf_len = buf.len();
-        if buf_len > 4 {
-            let packet_size = buf.get_i32_le();
-            buf_len-=4;
-            let buffer_size = i32::try_from(buf_len)?;
-            if packet_size <= buffer_size {
-                let payload_size = usize::try_from(packet_size-10)?;
-                let id = buf.get_i32_le();
-                let p_type_i32= buf.get_i32_le();
-                let p_type = PacketType::from_i32(p_type_i32);
-                let mut payload_buf = Vec::with_capacity(payload_size);
-                let take = buf.take(payload_size);
-                payload_buf.put(take);
-                buf.get_u16(); // remove the null terminators
-                return Ok(Some(Packet{
-                    size:packet_size,
-                    id: id,
-                    p_type: p_type,
-                    body:payload_buf
-                }));
-            }
+    pub fn into_body(mut self) -> Vec<u8>{
+        return std::mem::take(&mut self.body);
+    }
+
+    // appends to dst so one buffer can be reused for every packet
+    pub fn serialize_into(&self, dst:&mut BytesMut) {
+        if let Ok(s_usize) = usize::try_from(self.size+4) {
+            dst.reserve(s_usize);
+        }
+        dst.put_i32_le(self.size);
+        dst.put_i32_le(self.id);
+        dst.put_i32_le(self.p_type.to_i32());
+        dst.extend_from_slice(&self.body);
+        dst.put_u8(0); // add terminators
+        dst.put_u8(0); // add terminators
+    }
+
+    pub fn deserialize(buf:&mut BytesMut) -> Result<Option<Self>, Box<dyn Error + Send + Sync>>{
+        // only peek at the length so partial frames stay intact in the buffer
+        if buf.len() < 4 {
+            return Ok(None);
+        }
+        let packet_size = i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        if packet_size < MIN_PACKET_SIZE || packet_size > MAX_PACKET_SIZE {
+            return Err(RconError::Protocol(ProtocolError::BadSize(packet_size)).into());
+        }
+        let frame_len = usize::try_from(packet_size)?+4;
+        if buf.len() < frame_len {
+            buf.reserve(frame_len-buf.len());
+            return Ok(None);
+        }
+        let payload_size = usize::try_from(packet_size-MIN_PACKET_SIZE)?;
+        let mut frame = buf.split_to(frame_len);
+        frame.advance(4);
+        let id = frame.get_i32_le();
+        let p_type_i32= frame.get_i32_le();
+        let p_type = PacketType::from_i32(p_type_i32);
+        let payload_buf = frame.split_to(payload_size).to_vec();
+        if frame[..] != [0, 0] {
+            return Err(RconError::Protocol(ProtocolError::BadTerminator([frame[0], frame[1]])).into());
+        }
+        return Ok(Some(Packet{
+            size:packet_size,
+            id: id,
+            p_type: p_type,
+            body:payload_buf
+        }));
+    }
+
+    // reports a frame cut off by the end of the stream
+    pub fn check_eof(buf:&BytesMut) -> Result<(), RconError>{
+        if buf.is_empty() {
+            return Ok(());
+        }
+        let expected = if buf.len() >= 4 {
+            let packet_size = i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+            usize::try_from(packet_size).unwrap_or(0)+4
+        } else {
+            4
+        };
+        Err(RconError::Protocol(ProtocolError::TruncatedFrame{expected:expected, received:buf.len()}))
+    }
+}
+
+// login bodies carry the password, wipe them instead of leaving them on the heap
+#[cfg(feature = "secrecy")]
+impl Drop for Packet {
+    fn drop(&mut self) {
+        if let PacketType::Login = self.p_type {
+            zeroize::Zeroize::zeroize(&mut self.body);
         }
-        Ok(None)
     }
 }
 
 impl From<Packet> for Vec<u8> {
 
     fn from(value: Packet) -> Vec<u8> {
-        let mut result = if let Ok(s_usize) = usize::try_from(&value.size+4) {
-            Vec::<u8>::with_capacity(s_usize)
-        } else {
-            Vec::<u8>::new()
-        };
-        result.extend_from_slice(&value.size.to_le_bytes());
-        result.extend_from_slice(&value.id.to_le_bytes());
-        result.extend_from_slice(&((value.p_type as i32).to_le_bytes()));
-        result.extend_from_slice(&value.body);
-        result.push(0); // add terminators
-        result.push(0); // add terminators
-        return result;
+        return Vec::from(&value);
     }
 }
 
 impl From<&Packet> for Vec<u8> {
 
     fn from(value: &Packet) -> Vec<u8> {
-        let mut result = if let Ok(s_usize) = usize::try_from(&value.size+4) {
-            Vec::<u8>::with_capacity(s_usize)
-        } else {
-            Vec::<u8>::new()
-        };
-        result.extend_from_slice(&value.size.to_le_bytes());
-        result.extend_from_slice(&value.id.to_le_bytes());
-        result.extend_from_slice(&((value.p_type as i32).to_le_bytes()));
-        result.extend_from_slice(&value.body);
-        result.push(0); // add terminators
-        result.push(0); // add terminators
-        return result;
-    }
-}
\ No newline at end of file
+        let mut result = BytesMut::new();
+        value.serialize_into(&mut result);
+        return result.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id:i32, packet_type:PacketType, payload:&str) -> Vec<u8> {
+        return Vec::from(Packet::with_id(id, packet_type, payload).unwrap());
+    }
+
+    fn protocol_error(result:Result<Option<Packet>, Box<dyn Error + Send + Sync>>) -> ProtocolError {
+        match result.err().expect("expected an error").downcast::<RconError>() {
+            Ok(e) => match *e {
+                RconError::Protocol(e) => return e,
+                e => panic!("expected a protocol error, got {}", e)
+            },
+            Err(e) => panic!("expected a protocol error, got {}", e)
+        }
+    }
+
+    fn assert_packet(packet:&Packet, id:i32, payload:&str) {
+        assert_eq!(*packet.get_id(), id);
+        assert_eq!(packet.get_p_type().to_i32(), PacketType::Response.to_i32());
+        assert_eq!(packet.get_body(), &payload.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn byte_by_byte_delivery() {
+        let bytes = frame(7, PacketType::Response, "There are 0 of a max of 20 players online");
+        let mut buf = BytesMut::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            buf.put_u8(*byte);
+            let result = Packet::deserialize(&mut buf).unwrap();
+            if i+1 < bytes.len() {
+                assert!(result.is_none(), "packet returned after {} of {} bytes", i+1, bytes.len());
+                assert_eq!(&buf[..], &bytes[..i+1]);
+            } else {
+                assert_packet(&result.expect("complete frame not returned"), 7, "There are 0 of a max of 20 players online");
+                assert!(buf.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn split_at_every_offset() {
+        let bytes = frame(42, PacketType::Response, "hello");
+        for offset in 0..bytes.len() {
+            let mut buf = BytesMut::from(&bytes[..offset]);
+            assert!(Packet::deserialize(&mut buf).unwrap().is_none(), "packet returned after {} bytes", offset);
+            assert_eq!(&buf[..], &bytes[..offset]);
+            buf.extend_from_slice(&bytes[offset..]);
+            assert_packet(&Packet::deserialize(&mut buf).unwrap().unwrap(), 42, "hello");
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn leaves_the_next_frame_in_the_buffer() {
+        let first = frame(1, PacketType::Response, "first");
+        let second = frame(2, PacketType::Response, "second");
+        let mut buf = BytesMut::from(&first[..]);
+        buf.extend_from_slice(&second[..3]);
+        assert_packet(&Packet::deserialize(&mut buf).unwrap().unwrap(), 1, "first");
+        assert_eq!(&buf[..], &second[..3]);
+        assert!(Packet::deserialize(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(&second[3..]);
+        assert_packet(&Packet::deserialize(&mut buf).unwrap().unwrap(), 2, "second");
+    }
+
+    #[test]
+    fn new_never_uses_the_reserved_id() {
+        let first = *Packet::new(PacketType::Command, "list").unwrap().get_id();
+        let second = *Packet::new(PacketType::Command, "list").unwrap().get_id();
+        assert!(first > 0 && second > 0);
+        assert_ne!(first, second);
+        assert_ne!(first, crate::ids::RESERVED_ID);
+    }
+
+    #[test]
+    fn undersized_packet() {
+        let mut buf = BytesMut::new();
+        buf.put_i32_le(MIN_PACKET_SIZE-1);
+        assert_eq!(protocol_error(Packet::deserialize(&mut buf)), ProtocolError::BadSize(MIN_PACKET_SIZE-1));
+    }
+
+    #[test]
+    fn oversized_packet() {
+        let mut buf = BytesMut::new();
+        buf.put_i32_le(MAX_PACKET_SIZE+1);
+        assert_eq!(protocol_error(Packet::deserialize(&mut buf)), ProtocolError::BadSize(MAX_PACKET_SIZE+1));
+    }
+
+    #[test]
+    fn negative_size() {
+        let mut buf = BytesMut::new();
+        buf.put_i32_le(-1);
+        assert_eq!(protocol_error(Packet::deserialize(&mut buf)), ProtocolError::BadSize(-1));
+    }
+
+    #[test]
+    fn bad_terminator() {
+        let mut bytes = frame(3, PacketType::Response, "hi");
+        let len = bytes.len();
+        bytes[len-2] = b'x';
+        let mut buf = BytesMut::from(&bytes[..]);
+        assert_eq!(protocol_error(Packet::deserialize(&mut buf)), ProtocolError::BadTerminator([b'x', 0]));
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let bytes = frame(4, PacketType::Response, "cut off");
+        let mut buf = BytesMut::from(&bytes[..8]);
+        assert!(Packet::deserialize(&mut buf).unwrap().is_none());
+        assert_eq!(Packet::check_eof(&buf).unwrap_err().to_string(), RconError::Protocol(ProtocolError::TruncatedFrame{expected:bytes.len(), received:8}).to_string());
+
+        let short = BytesMut::from(&bytes[..2]);
+        assert_eq!(Packet::check_eof(&short).unwrap_err().to_string(), RconError::Protocol(ProtocolError::TruncatedFrame{expected:4, received:2}).to_string());
+        assert!(Packet::check_eof(&BytesMut::new()).is_ok());
+    }
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+// a command together with what the server answered, e.g. for audit logs
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandResponse {
+    pub command: String,
+    pub body: String,
+    pub request_id: i32,
+    // response packets the body was reassembled from
+    pub packets: usize,
+    // includes any retries made by the retry policy
+    pub round_trip: Duration
+}
+
+impl CommandResponse {
+    pub fn new(command:&str, body:&str, request_id:i32) -> CommandResponse {
+        return CommandResponse {
+            command: command.to_string(),
+            body: body.to_string(),
+            request_id: request_id,
+            packets: 0,
+            round_trip: Duration::ZERO
+        };
+    }
+}
@@ -0,0 +1,68 @@
+use std::{error::Error, time::Duration};
+use rand::Rng;
+use tokio::io;
+
+use crate::rcon::{ConnectionClosedError, RconError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    // total number of tries, including the first one
+    pub attempts: u32,
+    // delay before the first retry, doubled for every following one
+    pub backoff: Duration,
+    // upper bound of the random delay added to every backoff
+    pub jitter: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        return RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(500),
+            jitter: Duration::from_millis(100)
+        };
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(attempts:u32, backoff:Duration) -> RetryPolicy {
+        return RetryPolicy {
+            attempts: attempts,
+            backoff: backoff,
+            jitter: Duration::ZERO
+        };
+    }
+
+    pub fn jitter(mut self, jitter:Duration) -> RetryPolicy {
+        self.jitter = jitter;
+        return self;
+    }
+
+    // delay before the given retry, starting at 1
+    pub(crate) fn delay(&self, retry:u32) -> Duration {
+        let backoff = self.backoff.saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        let jitter = self.jitter.mul_f64(rand::thread_rng().gen::<f64>());
+        return backoff.saturating_add(jitter);
+    }
+}
+
+// auth failures and bad requests would fail the same way again
+pub(crate) fn is_retryable(e:&(dyn Error + Send + Sync + 'static)) -> bool {
+    if e.is::<ConnectionClosedError>() {
+        return true;
+    }
+    if let Some(RconError::Timeout(_)) = e.downcast_ref::<RconError>() {
+        return true;
+    }
+    if let Some(e) = e.downcast_ref::<io::Error>() {
+        return matches!(e.kind(),
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::TimedOut
+        );
+    }
+    return false;
+}
@@ -0,0 +1,37 @@
+use std::{future::Future, time::Duration};
+
+// timers used by RconClient, with the futures-io feature they no longer need a tokio runtime
+// and the tokio feature can be turned off
+
+// std::time::Instant panics in the browser
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+#[cfg(all(feature = "tokio", not(feature = "futures-io")))]
+pub(crate) async fn sleep(duration:Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(all(feature = "tokio", not(feature = "futures-io")))]
+pub(crate) async fn timeout<F: Future>(duration:Duration, future:F) -> Result<F::Output, Elapsed> {
+    return tokio::time::timeout(duration, future).await.map_err(|_| Elapsed);
+}
+
+#[cfg(feature = "futures-io")]
+pub(crate) async fn sleep(duration:Duration) {
+    futures_timer::Delay::new(duration).await;
+}
+
+#[cfg(feature = "futures-io")]
+pub(crate) async fn timeout<F: Future>(duration:Duration, future:F) -> Result<F::Output, Elapsed> {
+    let future = std::pin::pin!(future);
+    return match futures_util::future::select(future, futures_timer::Delay::new(duration)).await {
+        futures_util::future::Either::Left((output, _)) => Ok(output),
+        futures_util::future::Either::Right(_) => Err(Elapsed)
+    };
+}
@@ -0,0 +1,167 @@
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
+use bytes::BytesMut;
+use tokio::{io::{self, AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream, ToSocketAddrs}, time::sleep};
+
+use crate::{ids::RESERVED_ID, rcon_packet::{Packet, PacketType}};
+
+// servers usually split responses around 4096 bytes
+const MAX_RESPONSE_CHUNK: usize = 4096;
+const FRAGMENT_DELAY: Duration = Duration::from_millis(1);
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+// knobs for the mock server to misbehave the way real servers do
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnectionOptions {
+    // writes packets in pieces of this many bytes so clients see partial reads
+    pub(crate) fragment_size: Option<usize>,
+    // closes the connection instead of answering any command after this many
+    pub(crate) disconnect_after: Option<usize>
+}
+
+pub struct RconServer<F> {
+    listener: TcpListener,
+    password: Arc<str>,
+    handler: Arc<F>,
+    options: ConnectionOptions
+}
+
+impl<F, Fut> RconServer<F>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = String> + Send + 'static
+{
+    pub async fn bind<A: ToSocketAddrs>(addr:A, password:&str, handler:F) -> io::Result<RconServer<F>> {
+        let listener = TcpListener::bind(addr).await?;
+        return Ok(RconServer {
+            listener: listener,
+            password: Arc::from(password),
+            handler: Arc::new(handler),
+            options: ConnectionOptions::default()
+        });
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn options(mut self, options:ConnectionOptions) -> RconServer<F> {
+        self.options = options;
+        return self;
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        return self.listener.local_addr();
+    }
+
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            // errors like a connection reset before it was accepted only concern that connection
+            let stream = match self.listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %e, "accept failed");
+                    if out_of_descriptors(&e) {
+                        // give running connections time to close and free some
+                        sleep(ACCEPT_BACKOFF).await;
+                    }
+                    continue;
+                }
+            };
+            let password = self.password.clone();
+            let handler = self.handler.clone();
+            let options = self.options;
+            tokio::spawn(async move {
+                let _ = handle_connection(stream, password, handler, options).await;
+            });
+        }
+    }
+}
+
+// EMFILE/ENFILE, or WSAEMFILE on windows
+fn out_of_descriptors(e:&io::Error) -> bool {
+    let code = match e.raw_os_error() {
+        Some(code) => code,
+        None => return false
+    };
+    if cfg!(windows) {
+        return code == 10024;
+    }
+    return code == 23 || code == 24;
+}
+
+fn invalid_data<E: ToString>(e:E) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+}
+
+async fn write_packet(stream:&mut TcpStream, id:i32, packet_type:PacketType, payload:&str, fragment_size:Option<usize>) -> io::Result<()> {
+    let packet = Packet::with_id(id, packet_type, payload).map_err(invalid_data)?;
+    let bytes:Vec<u8> = packet.into();
+    match fragment_size {
+        Some(size) => {
+            for chunk in bytes.chunks(size) {
+                stream.write_all(chunk).await?;
+                stream.flush().await?;
+                sleep(FRAGMENT_DELAY).await;
+            }
+            Ok(())
+        }
+        None => stream.write_all(&bytes).await
+    }
+}
+
+async fn write_response(stream:&mut TcpStream, id:i32, response:&str, fragment_size:Option<usize>) -> io::Result<()> {
+    let mut remaining = response;
+    loop {
+        let mut end = remaining.len().min(MAX_RESPONSE_CHUNK);
+        while !remaining.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, rest) = remaining.split_at(end);
+        write_packet(stream, id, PacketType::Response, chunk, fragment_size).await?;
+        if rest.is_empty() {
+            return Ok(());
+        }
+        remaining = rest;
+    }
+}
+
+async fn handle_connection<F, Fut>(mut stream:TcpStream, password:Arc<str>, handler:Arc<F>, options:ConnectionOptions) -> io::Result<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = String>
+{
+    stream.set_nodelay(true)?;
+    let mut buf = BytesMut::with_capacity(4096);
+    let mut authenticated = false;
+    let mut commands_handled = 0;
+    loop {
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Ok(());
+        }
+        while let Some(packet) = Packet::deserialize(&mut buf).map_err(invalid_data)? {
+            let id = *packet.get_id();
+            match packet.get_p_type() {
+                PacketType::Login => {
+                    authenticated = packet.get_body().as_slice() == password.as_bytes();
+                    // the auth response shares its type value with command requests
+                    let response_id = if authenticated { id } else { RESERVED_ID };
+                    write_packet(&mut stream, response_id, PacketType::Command, "", options.fragment_size).await?;
+                }
+                PacketType::Command if authenticated => {
+                    if options.disconnect_after == Some(commands_handled) {
+                        return stream.shutdown().await;
+                    }
+                    commands_handled += 1;
+                    let command = String::from_utf8_lossy(packet.get_body()).into_owned();
+                    let response = handler(command).await;
+                    write_response(&mut stream, id, &response, options.fragment_size).await?;
+                }
+                PacketType::Response if authenticated => {
+                    // mirror empty packets back so clients can detect the end of a response
+                    write_packet(&mut stream, id, PacketType::Response, "", options.fragment_size).await?;
+                }
+                _ => {
+                    return stream.shutdown().await;
+                }
+            }
+        }
+    }
+}
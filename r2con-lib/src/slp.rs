@@ -0,0 +1,215 @@
+use std::{error::{self, Error}, fmt, time::{Duration, Instant}};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream, time::timeout};
+
+const HANDSHAKE: i32 = 0x00;
+const STATUS_REQUEST: i32 = 0x00;
+const STATUS_RESPONSE: i32 = 0x00;
+const PING: i32 = 0x01;
+const PONG: i32 = 0x01;
+const STATUS_STATE: i32 = 1;
+// -1 lets the server pick, it is the convention for status pings
+const PROTOCOL_VERSION: i32 = -1;
+const MAX_PACKET_SIZE: i32 = 2097151;
+// for connecting and the whole exchange, a server that accepts but never answers would hang forever
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct SlpError{
+    message:String
+}
+
+impl fmt::Display for SlpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "server list ping error: {}", self.message)
+    }
+}
+
+impl error::Error for SlpError {}
+
+impl SlpError {
+    fn new(message:&str) -> SlpError {
+        return SlpError { message: message.to_string() };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub version: String,
+    pub protocol: i32,
+    pub motd: String,
+    pub online_players: i64,
+    pub max_players: i64,
+    pub latency: Duration
+}
+
+#[derive(Deserialize)]
+struct StatusVersion {
+    name: String,
+    protocol: i32
+}
+
+#[derive(Deserialize)]
+struct StatusPlayers {
+    max: i64,
+    online: i64
+}
+
+#[derive(Deserialize)]
+struct StatusJson {
+    version: StatusVersion,
+    players: Option<StatusPlayers>,
+    description: Option<Value>
+}
+
+fn write_varint(buf:&mut Vec<u8>, value:i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push(((value & 0x7F) | 0x80) as u8);
+        value >>= 7;
+    }
+}
+
+async fn read_varint(stream:&mut TcpStream) -> Result<i32, Box<dyn Error + Send + Sync>> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = stream.read_u8().await?;
+        value |= u32::from(byte & 0x7F) << (7*i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(SlpError::new("varint is too long").into())
+}
+
+fn decode_varint(cursor:&mut &[u8]) -> Result<i32, SlpError> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let current: &[u8] = *cursor;
+        let (byte, rest) = current.split_first()
+            .ok_or_else(|| SlpError::new("truncated packet"))?;
+        *cursor = rest;
+        value |= u32::from(byte & 0x7F) << (7*i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(SlpError::new("varint is too long"))
+}
+
+fn frame(packet_id:i32, data:&[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(data.len()+5);
+    write_varint(&mut packet, packet_id);
+    packet.extend_from_slice(data);
+    let mut framed = Vec::with_capacity(packet.len()+5);
+    write_varint(&mut framed, packet.len() as i32);
+    framed.extend_from_slice(&packet);
+    return framed;
+}
+
+async fn read_packet(stream:&mut TcpStream) -> Result<(i32, Vec<u8>), Box<dyn Error + Send + Sync>> {
+    let length = read_varint(stream).await?;
+    if length <= 0 || length > MAX_PACKET_SIZE {
+        return Err(SlpError::new("invalid packet length").into());
+    }
+    let mut packet = vec![0u8; usize::try_from(length)?];
+    stream.read_exact(&mut packet).await?;
+    let mut cursor = packet.as_slice();
+    let packet_id = decode_varint(&mut cursor)?;
+    Ok((packet_id, cursor.to_vec()))
+}
+
+fn flatten_text(value:&Value, output:&mut String) {
+    match value {
+        Value::String(text) => output.push_str(text),
+        Value::Array(values) => {
+            for value in values {
+                flatten_text(value, output);
+            }
+        }
+        Value::Object(component) => {
+            if let Some(text) = component.get("text") {
+                flatten_text(text, output);
+            }
+            if let Some(extra) = component.get("extra") {
+                flatten_text(extra, output);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub async fn status(host:&str, port:u16) -> Result<ServerStatus, Box<dyn Error + Send + Sync>> {
+    return status_with_timeout(host, port, Some(DEFAULT_TIMEOUT)).await;
+}
+
+// None waits as long as the server takes
+pub async fn status_with_timeout(host:&str, port:u16, deadline:Option<Duration>) -> Result<ServerStatus, Box<dyn Error + Send + Sync>> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return request_status(host, port).await
+    };
+    match timeout(deadline, request_status(host, port)).await {
+        Ok(result) => result,
+        Err(_) => Err(SlpError::new(&format!("no response within {}ms", deadline.as_millis())).into())
+    }
+}
+
+async fn request_status(host:&str, port:u16) -> Result<ServerStatus, Box<dyn Error + Send + Sync>> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, PROTOCOL_VERSION);
+    write_varint(&mut handshake, i32::try_from(host.len())?);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, STATUS_STATE);
+    stream.write_all(&frame(HANDSHAKE, &handshake)).await?;
+    stream.write_all(&frame(STATUS_REQUEST, &[])).await?;
+
+    let (packet_id, data) = read_packet(&mut stream).await?;
+    if packet_id != STATUS_RESPONSE {
+        return Err(SlpError::new("unexpected status response").into());
+    }
+    let mut cursor = data.as_slice();
+    let json_len = usize::try_from(decode_varint(&mut cursor)?)?;
+    if cursor.len() < json_len {
+        return Err(SlpError::new("truncated status response").into());
+    }
+    let status_json: StatusJson = serde_json::from_slice(&cursor[..json_len])?;
+
+    // the server echoes the token back, a different one means the pong answers something else
+    let token = rand::random::<i64>();
+    let start = Instant::now();
+    stream.write_all(&frame(PING, &token.to_be_bytes())).await?;
+    let (packet_id, data) = read_packet(&mut stream).await?;
+    if packet_id != PONG {
+        return Err(SlpError::new("unexpected pong response").into());
+    }
+    let latency = start.elapsed();
+    if data[..] != token.to_be_bytes()[..] {
+        return Err(SlpError::new("the pong doesn't echo the ping").into());
+    }
+
+    let mut motd = String::new();
+    if let Some(description) = &status_json.description {
+        flatten_text(description, &mut motd);
+    }
+    let (online_players, max_players) = match status_json.players {
+        Some(players) => (players.online, players.max),
+        None => (0, 0)
+    };
+    return Ok(ServerStatus {
+        version: status_json.version.name,
+        protocol: status_json.version.protocol,
+        motd: motd,
+        online_players: online_players,
+        max_players: max_players,
+        latency: latency
+    });
+}
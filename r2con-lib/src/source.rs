@@ -0,0 +1,182 @@
+// parser for the output of the Source engine `status` command
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcePlayer {
+    pub userid: u32,
+    pub name: String,
+    // BOT for bots
+    pub steamid: String,
+    // bots don't report a ping
+    pub ping: Option<u32>
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceStatus {
+    pub hostname: String,
+    pub map: String,
+    pub humans: u32,
+    pub bots: u32,
+    pub max_players: u32,
+    pub players: Vec<SourcePlayer>
+}
+
+// "2 humans, 1 bots (24 max)", the older "2 (24 max)" or csgo's "1 humans, 2 bots (10/0 max) (not hibernating)"
+fn parse_player_counts(value:&str, status:&mut SourceStatus) {
+    let (counts, max) = match value.split_once('(') {
+        Some(parts) => parts,
+        None => (value, "")
+    };
+    status.max_players = max.split(|c:char| !c.is_ascii_digit()).next().unwrap_or("").parse().unwrap_or(0);
+    for part in counts.split(',') {
+        let mut words = part.split_whitespace();
+        let count = words.next().and_then(|count| count.parse::<u32>().ok()).unwrap_or(0);
+        match words.next() {
+            Some(word) if word.starts_with("bot") => status.bots = count,
+            _ => status.humans = count
+        }
+    }
+}
+
+// `#      2 "Alice"  [U:1:12345]  05:12  40  0 active 1.2.3.4:27005`
+// csgo adds a slot number before the name and bots read `#  4 "Bot" BOT active 64`
+fn parse_player(line:&str) -> Option<SourcePlayer> {
+    let line = line.strip_prefix('#')?;
+    let name_start = line.find('"')?;
+    let name_end = line.rfind('"')?;
+    if name_end <= name_start {
+        return None;
+    }
+    let userid = line[..name_start].split_whitespace().next()?.parse().ok()?;
+    let mut fields = line[name_end+1..].split_whitespace();
+    let steamid = fields.next()?.to_string();
+    let ping = match steamid.as_str() {
+        "BOT" => None,
+        _ => fields.nth(1).and_then(|ping| ping.parse().ok())
+    };
+    return Some(SourcePlayer {
+        userid: userid,
+        name: line[name_start+1..name_end].to_string(),
+        steamid: steamid,
+        ping: ping
+    });
+}
+
+pub fn parse_status(output:&str) -> Option<SourceStatus> {
+    let mut status = SourceStatus::default();
+    let mut found_header = false;
+    for line in output.lines() {
+        if line.starts_with('#') {
+            if let Some(player) = parse_player(line) {
+                status.players.push(player);
+            }
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue
+        };
+        match key {
+            "hostname" => {
+                status.hostname = value.to_string();
+                found_header = true;
+            }
+            // "cp_badlands at: 0 x, 0 y, 0 z"
+            "map" => status.map = value.split_whitespace().next().unwrap_or("").to_string(),
+            "players" => parse_player_counts(value, &mut status),
+            _ => {}
+        }
+    }
+    if !found_header {
+        return None;
+    }
+    return Some(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TF2_STATUS: &str = "hostname: My TF2 Server
+version : 8622567/24 8622567 secure
+udp/ip  : 0.0.0.0:27015  (public ip: 1.2.3.4)
+steamid : [G:1:1234567] (85568392921234567)
+account : not logged in  (No account specified)
+map     : cp_badlands at: 0 x, 0 y, 0 z
+tags    : cp,increased_maxplayers
+players : 2 humans, 1 bots (24 max)
+edicts  : 672 used of 2048 max
+# userid name                uniqueid            connected ping loss state  adr
+#      2 \"Alice\"             [U:1:12345]         05:12       40    0 active 1.2.3.4:27005
+#      3 \"Bob \"the\" Builder\" [U:1:67890]         1:02:33     75    0 active 5.6.7.8:27005
+#      4 \"Bot01\"             BOT                                     active
+";
+
+    const CSGO_STATUS: &str = "hostname: Counter-Strike: Global Offensive
+version : 1.38.8.1/13881 1575/8853 secure  [G:1:3514712] 
+udp/ip  : 0.0.0.0:27015  (public ip: 1.2.3.4)
+os      :  Linux
+type    :  community dedicated
+map     : de_dust2
+players : 1 humans, 1 bots (10/0 max) (not hibernating)
+
+# userid name uniqueid connected ping loss state rate adr
+# 2 1 \"Alice\" STEAM_1:0:12345 02:10 40 0 active 196608 1.2.3.4:27005
+#3 \"Bot\" BOT active 64
+#end
+";
+
+    const CSS_STATUS: &str = "hostname: Counter-Strike: Source
+version : 7604890/24 7604890 secure
+udp/ip  : 1.2.3.4:27015  (public ip: 1.2.3.4)
+map     : de_dust2 at: 0 x, 0 y, 0 z
+players : 2 (24 max)
+
+# userid name                uniqueid            connected ping loss state  adr
+#      2 \"Alice\"             STEAM_0:1:12345     05:12       40    0 active 1.2.3.4:27005
+#      5 \"Bob\"               STEAM_0:0:67890     00:30       55    0 active 5.6.7.8:27005
+";
+
+    fn player(userid:u32, name:&str, steamid:&str, ping:Option<u32>) -> SourcePlayer {
+        return SourcePlayer { userid: userid, name: name.to_string(), steamid: steamid.to_string(), ping: ping };
+    }
+
+    #[test]
+    fn tf2() {
+        let status = parse_status(TF2_STATUS).unwrap();
+        assert_eq!(status.hostname, "My TF2 Server");
+        assert_eq!(status.map, "cp_badlands");
+        assert_eq!((status.humans, status.bots, status.max_players), (2, 1, 24));
+        assert_eq!(status.players, vec![
+            player(2, "Alice", "[U:1:12345]", Some(40)),
+            player(3, "Bob \"the\" Builder", "[U:1:67890]", Some(75)),
+            player(4, "Bot01", "BOT", None)
+        ]);
+    }
+
+    #[test]
+    fn csgo() {
+        let status = parse_status(CSGO_STATUS).unwrap();
+        assert_eq!(status.hostname, "Counter-Strike: Global Offensive");
+        assert_eq!(status.map, "de_dust2");
+        assert_eq!((status.humans, status.bots, status.max_players), (1, 1, 10));
+        // the slot number after the userid is skipped
+        assert_eq!(status.players, vec![
+            player(2, "Alice", "STEAM_1:0:12345", Some(40)),
+            player(3, "Bot", "BOT", None)
+        ]);
+    }
+
+    #[test]
+    fn css() {
+        let status = parse_status(CSS_STATUS).unwrap();
+        assert_eq!(status.hostname, "Counter-Strike: Source");
+        assert_eq!((status.humans, status.bots, status.max_players), (2, 0, 24));
+        assert_eq!(status.players.len(), 2);
+        assert_eq!(status.players[1], player(5, "Bob", "STEAM_0:0:67890", Some(55)));
+    }
+
+    #[test]
+    fn not_status() {
+        assert_eq!(parse_status("Unknown command \"stauts\""), None);
+    }
+}
@@ -0,0 +1,291 @@
+use std::{collections::HashMap, error::Error, net::SocketAddr, sync::{Arc, Mutex, MutexGuard}};
+#[cfg(feature = "tower")]
+use std::{future::Future, pin::Pin, task::{Context, Poll}};
+use futures_util::{stream::{SplitSink, SplitStream}, SinkExt, Stream, StreamExt};
+use tokio::{io, sync::{self, broadcast, mpsc}, task::JoinHandle, time::{sleep, timeout, Instant}};
+use tokio_util::codec::Framed;
+
+use crate::{codec::RconCodec, config::{RconConfig, Termination}, events::{emit, packet_stream, ConnectionEvent}, ids::{IdSource, RESERVED_ID}, rcon::{append_response, unwrap_io_error, ConnectionClosedError, PayloadTooLargeError, RconError}, rate_limit::RateLimiter, rcon_packet::{Packet, PacketType}, stats::{ClientStats, StatsRecorder}, transport::Transport};
+
+enum Fragment {
+    Body(Vec<u8>),
+    End
+}
+
+#[derive(Default)]
+struct Routes {
+    requests: HashMap<i32, mpsc::UnboundedSender<Fragment>>,
+    // dummy packet id -> id of the command it terminates
+    terminators: HashMap<i32, i32>,
+    closed: bool
+}
+
+type SharedRoutes = Arc<Mutex<Routes>>;
+type FramedTransport = Framed<Box<dyn Transport>, RconCodec>;
+
+fn lock(routes:&SharedRoutes) -> MutexGuard<'_, Routes> {
+    return routes.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+// removes the route if the command future is dropped before completing
+struct RouteGuard {
+    routes: SharedRoutes,
+    id: i32,
+    terminator: Option<i32>
+}
+
+impl Drop for RouteGuard {
+    fn drop(&mut self) {
+        let mut routes = lock(&self.routes);
+        routes.requests.remove(&self.id);
+        if let Some(terminator) = self.terminator {
+            routes.terminators.remove(&terminator);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandSender {
+    writer: Arc<sync::Mutex<SplitSink<FramedTransport, Packet>>>,
+    routes: SharedRoutes,
+    ids: Arc<dyn IdSource>,
+    config: RconConfig,
+    addr: SocketAddr,
+    stats: Arc<StatsRecorder>,
+    limiter: Option<Arc<RateLimiter>>,
+    unsolicited: broadcast::Sender<Packet>
+}
+
+impl CommandSender {
+    pub(crate) fn spawn(framed:FramedTransport, addr:SocketAddr, ids:Arc<dyn IdSource>, config:RconConfig, stats:Arc<StatsRecorder>, limiter:Option<Arc<RateLimiter>>, events:broadcast::Sender<ConnectionEvent>, unsolicited:broadcast::Sender<Packet>) -> (CommandSender, JoinHandle<()>) {
+        let (writer, reader) = framed.split();
+        let routes = SharedRoutes::default();
+        let task = tokio::spawn(read_loop(reader, routes.clone(), stats.clone(), events, unsolicited.clone()));
+        let sender = CommandSender {
+            writer: Arc::new(sync::Mutex::new(writer)),
+            routes: routes,
+            ids: ids,
+            config: config,
+            addr: addr,
+            stats: stats,
+            limiter: limiter,
+            unsolicited: unsolicited
+        };
+        return (sender, task);
+    }
+
+    pub fn get_address(&self) -> io::Result<SocketAddr> {
+        return Ok(self.addr);
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        return self.stats.snapshot();
+    }
+
+    // packets that matched no outstanding request, the stream ends once the read loop and every clone are dropped
+    pub fn subscribe_unsolicited(&self) -> impl Stream<Item = Packet> {
+        return packet_stream(self.unsolicited.subscribe());
+    }
+
+    pub async fn send_command(&self, command:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let output = self.send_command_raw(command).await?;
+        self.config.decode(&output)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "send_command", skip_all, fields(addr = %self.addr, command = command))]
+    pub async fn send_command_raw(&self, command:&str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        self.stats.record_commands(1);
+        let start = Instant::now();
+        let result = match self.config.command_timeout {
+            Some(deadline) => match timeout(deadline, self.send(command)).await {
+                Ok(result) => result,
+                Err(_) => Err(RconError::Timeout(deadline).into())
+            },
+            None => self.send(command).await
+        };
+        if result.is_ok() {
+            self.stats.record_latency(start.elapsed());
+        }
+        result
+    }
+
+    async fn send(&self, command:&str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let max = self.config.max_payload_size;
+        if command.len() > max {
+            return Err(PayloadTooLargeError{size:command.len(), max:max}.into());
+        }
+        let packet = Packet::with_id(self.next_id(), PacketType::Command, command)?;
+        let dummy_packet = match self.config.termination {
+            Termination::DummyPacket => Some(Packet::with_id(self.next_id(), PacketType::Response, "")?),
+            _ => None
+        };
+
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _guard = {
+            let mut routes = lock(&self.routes);
+            if routes.closed {
+                return Err(ConnectionClosedError{addr:self.addr}.into());
+            }
+            routes.requests.insert(*packet.get_id(), tx);
+            if let Some(dummy_packet) = &dummy_packet {
+                routes.terminators.insert(*dummy_packet.get_id(), *packet.get_id());
+            }
+            RouteGuard {
+                routes: self.routes.clone(),
+                id: *packet.get_id(),
+                terminator: dummy_packet.as_ref().map(|p| *p.get_id())
+            }
+        };
+
+        {
+            // hold the writer so the dummy packet directly follows its command
+            let mut writer = self.writer.lock().await;
+            trace_written(&packet);
+            self.stats.record_written(&packet);
+            match (&dummy_packet, self.config.packet_delay.is_zero()) {
+                // flushed together with the dummy packet in a single write
+                (Some(_), true) => writer.feed(packet).await?,
+                _ => writer.send(packet).await?
+            }
+            if let Some(dummy_packet) = dummy_packet {
+                if !self.config.packet_delay.is_zero() {
+                    sleep(self.config.packet_delay).await;
+                }
+                trace_written(&dummy_packet);
+                self.stats.record_written(&dummy_packet);
+                writer.send(dummy_packet).await?;
+            }
+        }
+
+        let max_response = self.config.max_response_size;
+        let closed = || -> Box<dyn Error + Send + Sync> { ConnectionClosedError{addr:self.addr}.into() };
+        match self.config.termination {
+            Termination::DummyPacket => {
+                let mut output = Vec::new();
+                loop {
+                    match rx.recv().await {
+                        Some(Fragment::Body(body)) => append_response(&mut output, &body, max_response)?,
+                        Some(Fragment::End) => return Ok(output),
+                        None => return Err(closed())
+                    }
+                }
+            }
+            Termination::SinglePacket => {
+                match rx.recv().await {
+                    Some(Fragment::Body(body)) => {
+                        let mut output = Vec::new();
+                        append_response(&mut output, &body, max_response)?;
+                        Ok(output)
+                    }
+                    _ => Err(closed())
+                }
+            }
+            Termination::Timeout(idle) => {
+                let mut output = Vec::new();
+                match rx.recv().await {
+                    Some(Fragment::Body(body)) => append_response(&mut output, &body, max_response)?,
+                    _ => return Err(closed())
+                };
+                while let Ok(Some(Fragment::Body(body))) = timeout(idle, rx.recv()).await {
+                    append_response(&mut output, &body, max_response)?;
+                }
+                Ok(output)
+            }
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        return lock(&self.routes).closed;
+    }
+
+    fn next_id(&self) -> i32 {
+        loop {
+            let id = self.ids.next_id();
+            if id != RESERVED_ID {
+                return id;
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn trace_written(packet:&Packet) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(id = *packet.get_id(), packet_type = packet.get_p_type().to_i32(), size = *packet.get_size(), "packet written");
+}
+
+// clones of the sender share the connection, so the futures don't borrow the service
+#[cfg(feature = "tower")]
+impl tower_service::Service<String> for CommandSender {
+    type Response = String;
+    type Error = RconError;
+    type Future = Pin<Box<dyn Future<Output = Result<String, RconError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), RconError>> {
+        if self.is_closed() {
+            let closed: Box<dyn Error + Send + Sync> = ConnectionClosedError{addr:self.addr}.into();
+            return Poll::Ready(Err(closed.into()));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, command: String) -> Self::Future {
+        let sender = self.clone();
+        Box::pin(async move {
+            sender.send_command(&command).await.map_err(RconError::from)
+        })
+    }
+}
+
+fn route(routes:&SharedRoutes, packet:Packet, unsolicited:&broadcast::Sender<Packet>) {
+    let mut routes = lock(routes);
+    let id = *packet.get_id();
+    if let Some(command_id) = routes.terminators.remove(&id) {
+        if let Some(tx) = routes.requests.remove(&command_id) {
+            let _ = tx.send(Fragment::End);
+        }
+        return;
+    }
+    match routes.requests.get(&id) {
+        Some(tx) => {
+            if let PacketType::Response = packet.get_p_type() {
+                let _ = tx.send(Fragment::Body(packet.into_body()));
+            }
+        }
+        // sending only fails when nobody is subscribed
+        None => {
+            let _ = unsolicited.send(packet);
+        }
+    }
+}
+
+fn close(routes:&SharedRoutes) {
+    let mut routes = lock(routes);
+    routes.closed = true;
+    // dropping the senders wakes every waiting command
+    routes.requests.clear();
+    routes.terminators.clear();
+}
+
+async fn read_loop(mut reader:SplitStream<FramedTransport>, routes:SharedRoutes, stats:Arc<StatsRecorder>, events:broadcast::Sender<ConnectionEvent>, unsolicited:broadcast::Sender<Packet>) {
+    let reason = loop {
+        match reader.next().await {
+            Some(Ok(packet)) => {
+                stats.record_read(&packet);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(id = *packet.get_id(), packet_type = packet.get_p_type().to_i32(), size = *packet.get_size(), "packet read");
+                route(&routes, packet, &unsolicited);
+            }
+            // a malformed packet or a failed read, the stream can't be trusted after either
+            Some(Err(e)) => break unwrap_io_error(e).to_string(),
+            None => break "connection closed".to_string()
+        }
+    };
+    #[cfg(feature = "tracing")]
+    tracing::debug!(reason = %reason, "read loop stopped");
+    close(&routes);
+    emit(&events, ConnectionEvent::Disconnected(reason));
+}
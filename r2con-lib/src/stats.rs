@@ -0,0 +1,108 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::Duration};
+
+use crate::rcon_packet::Packet;
+
+// upper bounds of the command latency buckets, slower commands land in the overflow bucket
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    // (upper bound, count) for each bucket in LATENCY_BUCKETS
+    pub buckets: Vec<(Duration, u64)>,
+    pub overflow: u64,
+    pub count: u64,
+    pub total: Duration
+}
+
+impl LatencyHistogram {
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        return Some(self.total.div_f64(self.count as f64));
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientStats {
+    pub commands_sent: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub reconnects: u64,
+    pub auth_failures: u64,
+    pub latency: LatencyHistogram
+}
+
+// shared between a client and the CommandSender it is split into
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    commands_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    reconnects: AtomicU64,
+    auth_failures: AtomicU64,
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len()+1],
+    latency_total_micros: AtomicU64
+}
+
+impl StatsRecorder {
+    pub(crate) fn record_written(&self, packet:&Packet) {
+        self.bytes_sent.fetch_add(frame_size(packet), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_read(&self, packet:&Packet) {
+        self.bytes_received.fetch_add(frame_size(packet), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_commands(&self, count:usize) {
+        self.commands_sent.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_latency(&self, latency:Duration) {
+        let bucket = LATENCY_BUCKETS.iter().position(|bound| latency <= *bound).unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_total_micros.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_auth_failure(&self) {
+        self.auth_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientStats {
+        let counts = self.latency_buckets.iter().map(|count| count.load(Ordering::Relaxed)).collect::<Vec<u64>>();
+        let latency = LatencyHistogram {
+            buckets: LATENCY_BUCKETS.iter().copied().zip(counts.iter().copied()).collect(),
+            overflow: counts[LATENCY_BUCKETS.len()],
+            count: counts.iter().sum(),
+            total: Duration::from_micros(self.latency_total_micros.load(Ordering::Relaxed))
+        };
+        return ClientStats {
+            commands_sent: self.commands_sent.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            auth_failures: self.auth_failures.load(Ordering::Relaxed),
+            latency: latency
+        };
+    }
+}
+
+// the size field does not count itself
+fn frame_size(packet:&Packet) -> u64 {
+    return (*packet.get_size() as u64)+4;
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, future, net::SocketAddr, sync::Arc};
+use tokio::{io, task::JoinHandle};
+
+use crate::server::{ConnectionOptions, RconServer};
+
+const UNKNOWN_COMMAND: &str = "Unknown or incomplete command";
+
+type Responder = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+pub struct MockServerBuilder {
+    password: String,
+    responses: HashMap<String, String>,
+    responder: Option<Responder>,
+    options: ConnectionOptions
+}
+
+impl MockServerBuilder {
+    pub fn response(mut self, command:&str, response:&str) -> MockServerBuilder {
+        self.responses.insert(command.to_string(), response.to_string());
+        return self;
+    }
+
+    pub fn handler<F: Fn(&str) -> String + Send + Sync + 'static>(mut self, handler:F) -> MockServerBuilder {
+        self.responder = Some(Arc::new(handler));
+        return self;
+    }
+
+    pub fn fragment(mut self, size:usize) -> MockServerBuilder {
+        self.options.fragment_size = Some(size.max(1));
+        return self;
+    }
+
+    pub fn disconnect_after(mut self, commands:usize) -> MockServerBuilder {
+        self.options.disconnect_after = Some(commands);
+        return self;
+    }
+
+    // canned responses win over the handler, anything else gets the vanilla unknown command reply
+    pub async fn start(self) -> io::Result<MockServer> {
+        let responses = self.responses;
+        let responder = self.responder;
+        let handler = move |command:String| {
+            let response = match (&responder, responses.get(&command)) {
+                (_, Some(response)) => response.clone(),
+                (Some(responder), None) => responder(&command),
+                (None, None) => UNKNOWN_COMMAND.to_string()
+            };
+            future::ready(response)
+        };
+        let server = RconServer::bind("127.0.0.1:0", &self.password, handler).await?.options(self.options);
+        let addr = server.local_addr()?;
+        let task = tokio::spawn(async move {
+            let _ = server.run().await;
+        });
+        return Ok(MockServer {
+            addr: addr,
+            password: self.password,
+            task: task
+        });
+    }
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    password: String,
+    task: JoinHandle<()>
+}
+
+impl MockServer {
+    pub fn builder(password:&str) -> MockServerBuilder {
+        return MockServerBuilder {
+            password: password.to_string(),
+            responses: HashMap::new(),
+            responder: None,
+            options: ConnectionOptions::default()
+        };
+    }
+
+    pub fn get_address(&self) -> SocketAddr {
+        return self.addr;
+    }
+
+    pub fn get_password(&self) -> &str {
+        return &self.password;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,15 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+
+// Send everywhere but in the browser, where the JS handles behind a WebSocket can't be Send
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSend for T {}
+
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + MaybeSend {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + MaybeSend> Transport for T {}
@@ -0,0 +1,90 @@
+use std::error::Error;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{io, net::TcpStream};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+const CLIENT_NAME: &str = "r2con";
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct WebRconRequest<'a> {
+    identifier: i32,
+    message: &'a str,
+    name: &'a str
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WebRconResponse {
+    identifier: i32,
+    message: String
+}
+
+pub struct WebRconClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    addr: String,
+    next_id: i32
+}
+
+impl WebRconClient {
+    pub async fn connect(addr:&str, password:&str) -> Result<WebRconClient, Box<dyn Error + Send + Sync>> {
+        let url = format!("ws://{}/{}", addr, encode_password(password));
+        let (stream, _) = connect_async(url).await?;
+        return Ok(WebRconClient {
+            stream: stream,
+            addr: addr.to_string(),
+            next_id: 1
+        });
+    }
+
+    pub fn get_address(&self) -> &str {
+        return &self.addr;
+    }
+
+    pub async fn close(mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+
+    pub async fn send_command(&mut self, command:&str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let id = self.next_id;
+        // identifiers <= 0 are used by the server for broadcasts
+        self.next_id = if self.next_id == i32::MAX { 1 } else { self.next_id + 1 };
+
+        let request = WebRconRequest {
+            identifier: id,
+            message: command,
+            name: CLIENT_NAME
+        };
+        self.stream.send(Message::Text(serde_json::to_string(&request)?)).await?;
+
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                Message::Text(text) => {
+                    let response: WebRconResponse = serde_json::from_str(&text)?;
+                    if response.identifier == id {
+                        return Ok(response.message);
+                    }
+                }
+                Message::Close(_) => {
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::ConnectionAborted, format!("connection to {} closed", self.addr)).into())
+    }
+}
+
+fn encode_password(password:&str) -> String {
+    let mut encoded = String::with_capacity(password.len());
+    for byte in password.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    return encoded;
+}
@@ -0,0 +1,17 @@
+use std::{error::Error, net::SocketAddr};
+use tokio::io;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+use ws_stream_wasm::WsMeta;
+
+use crate::{config::RconConfig, rcon::{Endpoint, RconClient}};
+
+// tunnels RCON packets over a binary WebSocket, e.g. to a websockify style proxy in front of the server
+pub async fn connect(url:&str, password:&str, config:RconConfig) -> Result<RconClient, Box<dyn Error + Send + Sync>> {
+    let (_, stream) = WsMeta::connect(url, None).await
+        .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()))?;
+    // Transport doesn't need Send on wasm32, see transport::MaybeSend
+    let transport = stream.into_io().compat();
+    // browsers don't expose the address behind the socket
+    let addr = SocketAddr::from(([0, 0, 0, 0], 0));
+    return RconClient::connect_with_config(transport, addr, password, config, Endpoint::Custom).await;
+}
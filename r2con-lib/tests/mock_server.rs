@@ -0,0 +1,52 @@
+use r2con::{testing::MockServer, RconAuthError, RconClient};
+
+#[tokio::test]
+async fn canned_responses() {
+    let server = MockServer::builder("hunter2")
+        .response("list", "There are 0 of a max of 20 players online: ")
+        .start().await.unwrap();
+    let mut client = RconClient::connect(server.get_address(), server.get_password()).await.unwrap();
+    assert_eq!(client.send_command("list").await.unwrap(), "There are 0 of a max of 20 players online: ");
+    assert_eq!(client.send_command("nope").await.unwrap(), "Unknown or incomplete command");
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn handler_answers_other_commands() {
+    let server = MockServer::builder("hunter2")
+        .response("seed", "Seed: [42]")
+        .handler(|command| command.to_uppercase())
+        .start().await.unwrap();
+    let mut client = RconClient::connect(server.get_address(), server.get_password()).await.unwrap();
+    assert_eq!(client.send_command("seed").await.unwrap(), "Seed: [42]");
+    assert_eq!(client.send_command("say hi").await.unwrap(), "SAY HI");
+}
+
+#[tokio::test]
+async fn wrong_password() {
+    let server = MockServer::builder("hunter2").start().await.unwrap();
+    let e = RconClient::connect(server.get_address(), "hunter3").await.err().unwrap();
+    assert!(e.is::<RconAuthError>());
+}
+
+#[tokio::test]
+async fn fragmented_packets() {
+    let response = "a".repeat(5000);
+    let server = MockServer::builder("hunter2")
+        .response("long", &response)
+        .fragment(3)
+        .start().await.unwrap();
+    let mut client = RconClient::connect(server.get_address(), server.get_password()).await.unwrap();
+    assert_eq!(client.send_command("long").await.unwrap(), response);
+}
+
+#[tokio::test]
+async fn disconnect_after() {
+    let server = MockServer::builder("hunter2")
+        .response("list", "There are 0 of a max of 20 players online: ")
+        .disconnect_after(1)
+        .start().await.unwrap();
+    let mut client = RconClient::connect(server.get_address(), server.get_password()).await.unwrap();
+    assert!(client.send_command("list").await.is_ok());
+    assert!(client.send_command("list").await.is_err());
+}